@@ -0,0 +1,950 @@
+//! The syntax tree of a pattern string.
+//!
+//! Use [`parse`] to turn a pattern string into an [`Ast`], inspect or rewrite
+//! its nodes, and then print it back into a pattern string with its
+//! [`Display`](core::fmt::Display) implementation or [`Ast::to_bytes`].
+
+use crate::{
+    Error, LUA_MAXCAPTURES, Result, Span,
+    engine::{Automata, ByteSet, Prefilter, Program},
+};
+use core::fmt;
+use std::{ops::Range, sync::OnceLock};
+
+/// Parses a pattern string into a syntax tree.
+///
+/// # Errors
+///
+/// If the pattern string is malformed, an [`Error`] is returned.
+pub fn parse(pattern: &[u8]) -> Result<Ast> {
+    let parsed = Parsed::new(pattern);
+    if let Some(error) = parsed.error() {
+        return Err(error);
+    }
+
+    // The nodes of the innermost capture group, and the starts and nodes of
+    // its parents
+    let mut nodes = Vec::new();
+    let mut parents = Vec::new();
+
+    if parsed.anchored {
+        nodes.push(Node {
+            kind: NodeKind::StartAnchor,
+            span: 0..1,
+        });
+    }
+
+    for Item { kind, span } in parsed.items {
+        let kind = match kind {
+            ItemKind::Single {
+                class,
+                quantifier: None,
+            } => NodeKind::Single(class),
+            ItemKind::Single {
+                class,
+                quantifier: Some(quantifier),
+            } => NodeKind::Quantified(class, quantifier),
+            ItemKind::OpenCapture => {
+                parents.push((span.start, core::mem::take(&mut nodes)));
+                continue;
+            }
+            ItemKind::PositionCapture { level } => NodeKind::PositionCapture { index: level + 1 },
+            ItemKind::CloseCapture { level } => {
+                let Some((start, parent)) = parents.pop() else {
+                    unreachable!("captures are checked by the parser");
+                };
+                let kind = NodeKind::Capture {
+                    index: level + 1,
+                    nodes: core::mem::replace(&mut nodes, parent),
+                };
+                nodes.push(Node {
+                    kind,
+                    span: start..span.end,
+                });
+                continue;
+            }
+            ItemKind::Balance { open, close } => NodeKind::Balance { open, close },
+            ItemKind::Frontier(set) => NodeKind::Frontier(set),
+            ItemKind::BackReference { level } => NodeKind::BackReference { index: level + 1 },
+            ItemKind::EndAnchor => NodeKind::EndAnchor,
+        };
+        nodes.push(Node { kind, span });
+    }
+
+    Ok(Ast { nodes })
+}
+
+/// The syntax tree of a pattern string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ast {
+    /// The top level nodes of the pattern.
+    pub nodes: Vec<Node>,
+}
+
+impl Ast {
+    /// Prints the syntax tree back into an equivalent pattern string.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_nodes(&mut out, &self.nodes);
+        out
+    }
+}
+
+impl fmt::Display for Ast {
+    /// Prints the syntax tree back into an equivalent pattern string. Bytes
+    /// which are not valid UTF-8 are replaced, so use [`Ast::to_bytes`] for
+    /// patterns that are not text.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
+
+/// A node of the syntax tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Node {
+    /// The type of the node.
+    pub kind: NodeKind,
+    /// The position of the node in the pattern string.
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for Node {
+    /// Prints the node back into an equivalent pattern string.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = Vec::new();
+        write_nodes(&mut out, core::slice::from_ref(self));
+        f.write_str(&String::from_utf8_lossy(&out))
+    }
+}
+
+/// The type of a syntax tree node.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum NodeKind {
+    /// A start anchor `^`. This is only valid as the first node of a pattern.
+    StartAnchor,
+    /// An end anchor `$`. This is only valid as the last node of a pattern.
+    EndAnchor,
+    /// A single character class, like `a`, `.`, `%a`, or `[a-z]`.
+    Single(Single),
+    /// A single character class with a quantifier, like `a*`.
+    Quantified(Single, Quantifier),
+    /// A substring capture `(...)`.
+    Capture {
+        /// The number of the capture, starting from 1.
+        index: usize,
+        /// The captured nodes.
+        nodes: Vec<Node>,
+    },
+    /// A current string position capture `()`.
+    PositionCapture {
+        /// The number of the capture, starting from 1.
+        index: usize,
+    },
+    /// A balanced match `%bxy`.
+    Balance {
+        /// The opening character.
+        open: u8,
+        /// The closing character.
+        close: u8,
+    },
+    /// A frontier `%f[set]`.
+    Frontier(Set),
+    /// A back-reference `%1` to a previous substring capture.
+    BackReference {
+        /// The number of the referenced capture, starting from 1.
+        index: usize,
+    },
+}
+
+/// A pattern item which matches a single character.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Single {
+    /// Any character `.`.
+    Any,
+    /// A literal character, like `a` or `%.`.
+    Literal(u8),
+    /// A character class, like `%a`.
+    Class(Class),
+    /// A character set, like `[a-z]`.
+    Set(Set),
+}
+
+impl Single {
+    /// Checks whether the given character matches.
+    #[must_use]
+    pub fn matches(&self, c: u8) -> bool {
+        match self {
+            Single::Any => true,
+            Single::Literal(b) => *b == c,
+            Single::Class(class) => class.matches(c),
+            Single::Set(set) => set.matches(c),
+        }
+    }
+
+    /// Returns the set of characters which match.
+    pub(crate) fn bytes(&self) -> ByteSet {
+        match self {
+            Single::Any => ByteSet::FULL,
+            Single::Literal(c) => {
+                let mut bytes = ByteSet::EMPTY;
+                bytes.insert(*c);
+                bytes
+            }
+            Single::Class(class) => class.bytes(),
+            Single::Set(set) => set.bytes(),
+        }
+    }
+}
+
+/// A quantifier for a single character item.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Quantifier {
+    /// Zero or more, greedily `*`.
+    ZeroOrMore,
+    /// One or more, greedily `+`.
+    OneOrMore,
+    /// Zero or more, lazily `-`.
+    Lazy,
+    /// Zero or one `?`.
+    Optional,
+}
+
+impl Quantifier {
+    fn from_byte(c: u8) -> Option<Self> {
+        Some(match c {
+            b'*' => Self::ZeroOrMore,
+            b'+' => Self::OneOrMore,
+            b'-' => Self::Lazy,
+            b'?' => Self::Optional,
+            _ => return None,
+        })
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::ZeroOrMore => b'*',
+            Self::OneOrMore => b'+',
+            Self::Lazy => b'-',
+            Self::Optional => b'?',
+        }
+    }
+}
+
+/// A character class, like `%a` or its complement `%A`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Class {
+    /// The type of the class.
+    pub kind: ClassKind,
+    /// Whether the class is complemented.
+    pub negated: bool,
+}
+
+impl Class {
+    /// Checks whether the given character matches.
+    #[must_use]
+    pub const fn matches(self, c: u8) -> bool {
+        let matches = match self.kind {
+            ClassKind::Letter => c.is_ascii_alphabetic(),
+            ClassKind::Control => c.is_ascii_control(),
+            ClassKind::Digit => c.is_ascii_digit(),
+            ClassKind::Printable => c.is_ascii_graphic(),
+            ClassKind::Lowercase => c.is_ascii_lowercase(),
+            ClassKind::Punctuation => c.is_ascii_punctuation(),
+            ClassKind::Space => c.is_ascii_whitespace(),
+            ClassKind::Uppercase => c.is_ascii_uppercase(),
+            ClassKind::Alphanumeric => c.is_ascii_alphanumeric(),
+            ClassKind::HexDigit => c.is_ascii_hexdigit(),
+            ClassKind::Zero => c == 0,
+        };
+        matches != self.negated
+    }
+
+    /// Returns the set of characters which match.
+    pub(crate) fn bytes(self) -> ByteSet {
+        let bytes = CLASS_BYTES[self.kind as usize];
+        if self.negated {
+            bytes.complement()
+        } else {
+            bytes
+        }
+    }
+
+    /// Converts an escaped character into a class, if it is one.
+    pub(crate) fn from_byte(c: u8) -> Option<Self> {
+        let kind = match c.to_ascii_lowercase() {
+            b'a' => ClassKind::Letter,
+            b'c' => ClassKind::Control,
+            b'd' => ClassKind::Digit,
+            b'g' => ClassKind::Printable,
+            b'l' => ClassKind::Lowercase,
+            b'p' => ClassKind::Punctuation,
+            b's' => ClassKind::Space,
+            b'u' => ClassKind::Uppercase,
+            b'w' => ClassKind::Alphanumeric,
+            b'x' => ClassKind::HexDigit,
+            b'z' => ClassKind::Zero,
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            negated: c.is_ascii_uppercase(),
+        })
+    }
+
+    fn to_byte(self) -> u8 {
+        let c = match self.kind {
+            ClassKind::Letter => b'a',
+            ClassKind::Control => b'c',
+            ClassKind::Digit => b'd',
+            ClassKind::Printable => b'g',
+            ClassKind::Lowercase => b'l',
+            ClassKind::Punctuation => b'p',
+            ClassKind::Space => b's',
+            ClassKind::Uppercase => b'u',
+            ClassKind::Alphanumeric => b'w',
+            ClassKind::HexDigit => b'x',
+            ClassKind::Zero => b'z',
+        };
+        if self.negated {
+            c.to_ascii_uppercase()
+        } else {
+            c
+        }
+    }
+}
+
+/// The type of a character class.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClassKind {
+    /// All letters `%a`.
+    Letter,
+    /// All control characters `%c`.
+    Control,
+    /// All digits `%d`.
+    Digit,
+    /// All printable characters except space `%g`.
+    Printable,
+    /// All lowercase letters `%l`.
+    Lowercase,
+    /// All punctuation characters `%p`.
+    Punctuation,
+    /// All space characters `%s`.
+    Space,
+    /// All uppercase letters `%u`.
+    Uppercase,
+    /// All alphanumeric characters `%w`.
+    Alphanumeric,
+    /// All hexadecimal digits `%x`.
+    HexDigit,
+    /// The character with representation 0 `%z`.
+    Zero,
+}
+
+/// The characters which each type of class matches, in the order of
+/// [`ClassKind`].
+const CLASS_BYTES: [ByteSet; 11] = [
+    class_bytes(ClassKind::Letter),
+    class_bytes(ClassKind::Control),
+    class_bytes(ClassKind::Digit),
+    class_bytes(ClassKind::Printable),
+    class_bytes(ClassKind::Lowercase),
+    class_bytes(ClassKind::Punctuation),
+    class_bytes(ClassKind::Space),
+    class_bytes(ClassKind::Uppercase),
+    class_bytes(ClassKind::Alphanumeric),
+    class_bytes(ClassKind::HexDigit),
+    class_bytes(ClassKind::Zero),
+];
+
+/// Returns the set of characters which a class of the given type matches.
+const fn class_bytes(kind: ClassKind) -> ByteSet {
+    let class = Class {
+        kind,
+        negated: false,
+    };
+    let mut bytes = ByteSet::EMPTY;
+    let mut c = 0;
+    loop {
+        if class.matches(c) {
+            bytes.insert(c);
+        }
+        if c == u8::MAX {
+            return bytes;
+        }
+        c += 1;
+    }
+}
+
+/// A character set `[set]`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Set {
+    /// Whether the set is complemented `[^set]`.
+    pub negated: bool,
+    /// The items of the set.
+    pub items: Vec<SetItem>,
+}
+
+impl Set {
+    /// Checks whether the given character matches.
+    #[must_use]
+    pub fn matches(&self, c: u8) -> bool {
+        self.items.iter().any(|item| item.matches(c)) != self.negated
+    }
+
+    /// Returns the set of characters which match.
+    pub(crate) fn bytes(&self) -> ByteSet {
+        let mut bytes = ByteSet::EMPTY;
+        for item in &self.items {
+            match *item {
+                SetItem::Literal(c) => bytes.insert(c),
+                SetItem::Range(first, last) => bytes.insert_range(first, last),
+                SetItem::Class(class) => bytes = bytes.union(class.bytes()),
+            }
+        }
+        if self.negated {
+            bytes.complement()
+        } else {
+            bytes
+        }
+    }
+
+    /// Parses the set between the given bracket positions of the pattern.
+    fn parse(pattern: &[u8], mut p: usize, end: usize) -> Self {
+        let negated = pattern[p + 1] == b'^';
+        p += 1 + usize::from(negated);
+
+        let mut items = Vec::new();
+        while p < end {
+            if pattern[p] == b'%' {
+                // %w
+                items.push(
+                    Class::from_byte(pattern[p + 1])
+                        .map_or(SetItem::Literal(pattern[p + 1]), SetItem::Class),
+                );
+                p += 2;
+            } else if pattern[p + 1] == b'-' && p + 2 < end {
+                // [a-z]
+                items.push(SetItem::Range(pattern[p], pattern[p + 2]));
+                p += 3;
+            } else {
+                // Literal character
+                items.push(SetItem::Literal(pattern[p]));
+                p += 1;
+            }
+        }
+
+        Self { negated, items }
+    }
+}
+
+/// An item of a character set.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SetItem {
+    /// A literal character, like `a` or `%]`.
+    Literal(u8),
+    /// An inclusive range of characters, like `a-z`.
+    Range(u8, u8),
+    /// A character class, like `%a`.
+    Class(Class),
+}
+
+impl SetItem {
+    /// Checks whether the given character matches.
+    #[must_use]
+    pub const fn matches(self, c: u8) -> bool {
+        match self {
+            SetItem::Literal(b) => b == c,
+            SetItem::Range(first, last) => first <= c && c <= last,
+            SetItem::Class(class) => class.matches(c),
+        }
+    }
+}
+
+/// A pattern string split into a flat list of items, which is the form used
+/// by the matching engine.
+#[derive(Debug)]
+pub(crate) struct Parsed {
+    /// Whether the pattern is anchored to the start position.
+    pub anchored: bool,
+    /// The pattern items, in order, up to the first syntax error.
+    pub items: Vec<Item>,
+    /// The first syntax error in the pattern, and the position of the item
+    /// which contains it.
+    pub error: Option<(usize, Error)>,
+    /// The position of the first capture group which is never closed.
+    pub unfinished: Option<usize>,
+    /// The length of the pattern string.
+    pub len: usize,
+    /// The scan for candidate start positions of a match.
+    pub prefilter: Prefilter,
+    /// Whether the pattern is a plain string without any special items, so
+    /// that the prefilter alone finds its matches.
+    pub literal: bool,
+    /// The pattern items compiled for the matching VM.
+    pub program: Program,
+    /// The number of capture groups.
+    pub captures: usize,
+    /// Whether the pattern is only used for a single call, like those of the
+    /// free functions, which is rarely long enough to be worth building the
+    /// automata.
+    pub one_shot: bool,
+    /// The automata of a regular pattern, which are built the first time
+    /// they are needed.
+    automata: OnceLock<Automata>,
+}
+
+impl Parsed {
+    /// Splits a pattern string into its items. Parsing stops at the first
+    /// syntax error, which is recorded instead of being returned, since Lua
+    /// only reports an error once the matcher actually reaches the malformed
+    /// part of the pattern.
+    pub fn new(pattern: &[u8]) -> Self {
+        let mut parser = Parser::new(pattern);
+        let offset = parser.offset;
+
+        let mut error = None;
+        let mut p = 0;
+        while p < parser.pattern.len() {
+            match parser.item(p) {
+                Ok(next) => p = next,
+                Err(e) => {
+                    error = Some((p + offset, e));
+                    break;
+                }
+            }
+        }
+
+        let anchored = offset != 0;
+        let literal = !anchored
+            && error.is_none()
+            && parser.open.is_empty()
+            && !parser.items.is_empty()
+            && parser.items.iter().all(|item| {
+                matches!(
+                    item.kind,
+                    ItemKind::Single {
+                        class: Single::Literal(_),
+                        quantifier: None,
+                    }
+                )
+            });
+
+        let program = Program::new(&parser.items, error.is_some());
+
+        Self {
+            anchored,
+            prefilter: Prefilter::new(anchored, &parser.items),
+            literal,
+            program,
+            captures: parser.level,
+            one_shot: false,
+            automata: OnceLock::new(),
+            items: parser.items,
+            error,
+            unfinished: parser.open.first().map(|(_, pos)| pos + offset),
+            len: pattern.len(),
+        }
+    }
+
+    /// Checks whether the pattern has no syntax errors and is regular, so
+    /// that the automata can match it.
+    pub fn is_regular(&self) -> bool {
+        self.error.is_none() && self.unfinished.is_none() && self.program.regular
+    }
+
+    /// Returns the automata of a regular pattern, building them the first
+    /// time they are needed.
+    pub fn automata(&self) -> Option<&Automata> {
+        self.is_regular().then(|| {
+            self.automata
+                .get_or_init(|| Automata::new(&self.program, self.literal))
+        })
+    }
+
+    /// Returns the span of the item at the given index.
+    pub fn span(&self, index: usize) -> Span {
+        Span::pattern(if let Some(item) = self.items.get(index) {
+            item.span.clone()
+        } else {
+            let pos = self.error.as_ref().map_or(self.len, |(pos, _)| *pos);
+            pos..pos
+        })
+    }
+
+    /// Returns the first syntax error in the pattern, if there is one.
+    pub fn error(&self) -> Option<Error> {
+        if let Some((_, error)) = &self.error {
+            Some(error.clone())
+        } else {
+            self.unfinished.map(|_| self.unfinished_error())
+        }
+    }
+
+    /// Returns the error for a capture group which is never closed.
+    pub fn unfinished_error(&self) -> Error {
+        let pos = self.unfinished.unwrap_or(self.len);
+        Error::UnfinishedCapture {
+            span: Span::pattern(pos..pos + 1),
+        }
+    }
+}
+
+/// Finds every syntax error in a pattern string, in order. Unlike [`Parsed`],
+/// which stops at the first error, this skips past each error and keeps
+/// parsing the rest of the pattern.
+pub(crate) fn errors(pattern: &[u8]) -> Vec<Error> {
+    let mut parser = Parser::new(pattern);
+    let mut errors = Vec::new();
+    let mut p = 0;
+    while p < parser.pattern.len() {
+        match parser.item(p) {
+            Ok(next) => p = next,
+            Err(e) => {
+                p = parser.recover(p, &e);
+                errors.push(e);
+            }
+        }
+    }
+
+    errors.extend(parser.open.iter().map(|(_, pos)| {
+        let pos = pos + parser.offset;
+        Error::UnfinishedCapture {
+            span: Span::pattern(pos..pos + 1),
+        }
+    }));
+    errors.sort_by_key(|error| error.span().range.start);
+    errors
+}
+
+/// A single item of a pattern.
+#[derive(Debug)]
+pub(crate) struct Item {
+    /// The type of the item.
+    pub kind: ItemKind,
+    /// The position of the item in the pattern string.
+    pub span: Range<usize>,
+}
+
+#[derive(Debug)]
+pub(crate) enum ItemKind {
+    /// A single character class with an optional quantifier.
+    Single {
+        class: Single,
+        quantifier: Option<Quantifier>,
+    },
+    /// The start of a substring capture group.
+    OpenCapture,
+    /// A current string position capture group.
+    PositionCapture { level: usize },
+    /// The end of the substring capture group at the given level.
+    CloseCapture { level: usize },
+    /// A balanced match `%bxy`.
+    Balance { open: u8, close: u8 },
+    /// A frontier `%f[set]`.
+    Frontier(Set),
+    /// A back-reference `%1` to the finished capture group at the given level.
+    BackReference { level: usize },
+    /// An end of subject anchor `$`.
+    EndAnchor,
+}
+
+struct Parser<'a> {
+    /// The pattern to parse, without any leading anchor.
+    pattern: &'a [u8],
+    /// The length of the leading anchor, which is added to spans.
+    offset: usize,
+    /// The parsed items.
+    items: Vec<Item>,
+    /// Number of capture groups.
+    level: usize,
+    /// The levels and positions of capture groups still waiting to be closed.
+    open: Vec<(usize, usize)>,
+    /// Bit mask of position capture group levels.
+    positions: u64,
+}
+
+impl<'a> Parser<'a> {
+    fn new(pattern: &'a [u8]) -> Self {
+        let offset = usize::from(pattern.first() == Some(&b'^'));
+        Self {
+            pattern: &pattern[offset..],
+            offset,
+            items: Vec::new(),
+            level: 0,
+            open: Vec::new(),
+            positions: 0,
+        }
+    }
+
+    /// Parses the item at the given position of the pattern. Returns the
+    /// position of the next item if successful.
+    fn item(&mut self, p: usize) -> Result<usize> {
+        match self.pattern[p] {
+            b'(' => {
+                let (next, is_position) = if self.pattern.get(p + 1) == Some(&b')') {
+                    (p + 2, true)
+                } else {
+                    (p + 1, false)
+                };
+
+                if self.level >= LUA_MAXCAPTURES {
+                    return Err(Error::TooManyCaptures {
+                        span: self.span(p..next),
+                    });
+                }
+
+                let level = self.level;
+                self.level += 1;
+                let kind = if is_position {
+                    self.positions |= 1 << level;
+                    ItemKind::PositionCapture { level }
+                } else {
+                    self.open.push((level, p));
+                    ItemKind::OpenCapture
+                };
+                return Ok(self.push(kind, p, next));
+            }
+            b')' => {
+                let Some((level, _)) = self.open.pop() else {
+                    return Err(Error::InvalidPatternCapture {
+                        span: self.span(p..p + 1),
+                    });
+                };
+                return Ok(self.push(ItemKind::CloseCapture { level }, p, p + 1));
+            }
+            b'$' if p + 1 == self.pattern.len() => {
+                return Ok(self.push(ItemKind::EndAnchor, p, p + 1));
+            }
+            b'%' => match self.pattern.get(p + 1).copied() {
+                Some(b'b') => {
+                    if p + 3 >= self.pattern.len() {
+                        return Err(Error::MissingBalanceArgs {
+                            span: self.span(p..self.pattern.len()),
+                        });
+                    }
+                    let kind = ItemKind::Balance {
+                        open: self.pattern[p + 2],
+                        close: self.pattern[p + 3],
+                    };
+                    return Ok(self.push(kind, p, p + 4));
+                }
+                Some(b'f') => {
+                    let start = p + 2;
+                    if self.pattern.get(start) != Some(&b'[') {
+                        return Err(Error::IncompleteFrontier {
+                            span: self.span(p..start),
+                        });
+                    }
+                    let next = self.class_end(start)?;
+                    let set = Set::parse(self.pattern, start, next - 1);
+                    return Ok(self.push(ItemKind::Frontier(set), p, next));
+                }
+                Some(digit @ b'0'..=b'9') => {
+                    let level = self.check_capture(p, digit)?;
+                    return Ok(self.push(ItemKind::BackReference { level }, p, p + 2));
+                }
+                _ => {
+                    // This is actually a single character class, so handle it
+                    // below.
+                }
+            },
+            _ => {
+                // This is actually a normal character, so handle it below.
+            }
+        }
+
+        let class_end = self.class_end(p)?;
+        let class = match self.pattern[p] {
+            b'.' => Single::Any,
+            b'%' => {
+                let c = self.pattern[p + 1];
+                Class::from_byte(c).map_or(Single::Literal(c), Single::Class)
+            }
+            b'[' => Single::Set(Set::parse(self.pattern, p, class_end - 1)),
+            c => Single::Literal(c),
+        };
+
+        let quantifier = self
+            .pattern
+            .get(class_end)
+            .copied()
+            .and_then(Quantifier::from_byte);
+        let next = class_end + usize::from(quantifier.is_some());
+
+        Ok(self.push(ItemKind::Single { class, quantifier }, p, next))
+    }
+
+    /// Skips past a syntax error in the item at the given position. Returns
+    /// the position at which to resume parsing.
+    fn recover(&mut self, p: usize, error: &Error) -> usize {
+        let range = &error.span().range;
+        match error {
+            // Treat the bracket as a literal, since the rest of the pattern
+            // is probably not meant to be part of the set
+            Error::EndsWithoutBracket { .. } => range.start - self.offset + 1,
+            Error::TooManyCaptures { .. } => {
+                // Track the group anyway so its closing parenthesis is not
+                // reported too
+                if range.len() == 1 {
+                    self.open.push((usize::MAX, p));
+                }
+                range.end - self.offset
+            }
+            _ => range.end - self.offset,
+        }
+    }
+
+    /// Converts a range of the pattern without its leading anchor to a span
+    /// of the whole pattern.
+    fn span(&self, range: Range<usize>) -> Span {
+        Span::pattern(range.start + self.offset..range.end + self.offset)
+    }
+
+    /// Adds a parsed item and returns the position of the next item.
+    fn push(&mut self, kind: ItemKind, start: usize, next: usize) -> usize {
+        self.items.push(Item {
+            kind,
+            span: start + self.offset..next + self.offset,
+        });
+        next
+    }
+
+    /// Ensures the given capture index belongs to a finished capture group and
+    /// returns its level if so.
+    fn check_capture(&self, p: usize, digit: u8) -> Result<usize> {
+        let index = usize::from(digit - b'0');
+        if let Some(level) = index.checked_sub(1)
+            && level < self.level
+            && self.positions & (1 << level) == 0
+            && !self.open.iter().any(|(open, _)| *open == level)
+        {
+            Ok(level)
+        } else {
+            Err(Error::InvalidCaptureIndex {
+                span: self.span(p..p + 2),
+                index,
+            })
+        }
+    }
+
+    /// Finds the end of a character class. Returns the next position of the
+    /// pattern, or an error if the pattern ends before the class is complete.
+    fn class_end(&self, mut p: usize) -> Result<usize> {
+        let start = p;
+        let c = self.pattern[p];
+        p += 1;
+        Ok(match c {
+            b'%' => {
+                if p == self.pattern.len() {
+                    return Err(Error::EndsWithPercent {
+                        span: self.span(start..p),
+                    });
+                }
+                p + 1
+            }
+            b'[' => {
+                // It is possible that we are at the end of the pattern.
+                if self.pattern.get(p).copied().unwrap_or(b'\0') == b'^' {
+                    p += 1;
+                }
+
+                loop {
+                    if p == self.pattern.len() {
+                        return Err(Error::EndsWithoutBracket {
+                            span: self.span(start..p),
+                        });
+                    }
+                    p += 1;
+                    if self.pattern[p - 1] == b'%' && p < self.pattern.len() {
+                        p += 1;
+                    }
+                    // It is possible that we are at the end of the pattern.
+                    if self.pattern.get(p).copied().unwrap_or(b'\0') == b']' {
+                        break;
+                    }
+                }
+
+                p + 1
+            }
+            _ => p,
+        })
+    }
+}
+
+/// Writes nodes as a pattern string.
+fn write_nodes(out: &mut Vec<u8>, nodes: &[Node]) {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::StartAnchor => out.push(b'^'),
+            NodeKind::EndAnchor => out.push(b'$'),
+            NodeKind::Single(single) => write_single(out, single),
+            NodeKind::Quantified(single, quantifier) => {
+                write_single(out, single);
+                out.push(quantifier.to_byte());
+            }
+            NodeKind::Capture { nodes, .. } => {
+                out.push(b'(');
+                write_nodes(out, nodes);
+                out.push(b')');
+            }
+            NodeKind::PositionCapture { .. } => out.extend(b"()"),
+            NodeKind::Balance { open, close } => out.extend([b'%', b'b', *open, *close]),
+            NodeKind::Frontier(set) => {
+                out.extend(b"%f");
+                write_set(out, set);
+            }
+            NodeKind::BackReference { index } => {
+                out.push(b'%');
+                out.extend(index.to_string().as_bytes());
+            }
+        }
+    }
+}
+
+/// Writes a single character item as a pattern string.
+fn write_single(out: &mut Vec<u8>, single: &Single) {
+    match single {
+        Single::Any => out.push(b'.'),
+        Single::Literal(c) => write_literal(out, *c),
+        Single::Class(class) => out.extend([b'%', class.to_byte()]),
+        Single::Set(set) => write_set(out, set),
+    }
+}
+
+/// Writes a character set as a pattern string.
+fn write_set(out: &mut Vec<u8>, set: &Set) {
+    out.push(b'[');
+    if set.negated {
+        out.push(b'^');
+    }
+    for item in &set.items {
+        match *item {
+            SetItem::Literal(c) => write_literal(out, c),
+            // An empty range matches nothing, so write one which does not
+            // need escaping
+            SetItem::Range(first, last) if first > last => out.extend(b"1-0"),
+            // These characters are special at the ends of a range, so write
+            // each character of the range separately instead
+            SetItem::Range(first, last)
+                if matches!(first, b'%' | b']' | b'^' | b'-')
+                    || matches!(last, b'%' | b']' | b'^') =>
+            {
+                for c in first..=last {
+                    write_literal(out, c);
+                }
+            }
+            SetItem::Range(first, last) => out.extend([first, b'-', last]),
+            SetItem::Class(class) => out.extend([b'%', class.to_byte()]),
+        }
+    }
+    out.push(b']');
+}
+
+/// Writes a literal character, escaping it if it might be special.
+fn write_literal(out: &mut Vec<u8>, c: u8) {
+    if b"^$*+?.()[]%-".contains(&c) {
+        out.push(b'%');
+    }
+    out.push(c);
+}
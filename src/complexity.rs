@@ -0,0 +1,185 @@
+use crate::{
+    Result, Span,
+    ast::{ItemKind, Parsed, Quantifier},
+};
+use std::ops::Range;
+
+/// Estimates how much work a backtracking matcher like Lua's can do in the
+/// worst case to match a pattern, for an input of length n.
+///
+/// The estimate looks for runs of quantified items which can all match the
+/// same character, like `%d*%d*%d*$` or `.-.-.-x`, followed by an item which
+/// can fail. On a long run of that character, the matcher tries every way to
+/// split the run between the quantified items before it gives up, and does
+/// so again at every start position unless the pattern is anchored.
+///
+/// This crate matches most patterns with automata instead, and bounds the
+/// work of its backtracking matcher, so it does much less than the estimate
+/// for many patterns. The estimate is meant for rejecting patterns which are
+/// dangerous to run, here or in Lua.
+///
+/// # Errors
+///
+/// If the pattern string is malformed, the first [`Error`](crate::Error) in it
+/// is returned.
+pub fn analyze_complexity(pattern: &[u8]) -> Result<Complexity> {
+    let parsed = Parsed::new(pattern);
+    if let Some(error) = parsed.error() {
+        return Err(error);
+    }
+
+    let mut worst = Run::default();
+    for c in 0..=u8::MAX {
+        let mut run = Run::default();
+        for item in &parsed.items {
+            match role(&item.kind, c) {
+                Role::Empty | Role::Fixed => {}
+                Role::Unbounded { fails } => {
+                    run.items.push(item.span.clone());
+                    run.unbounded += 1;
+                    run.fails |= fails;
+                }
+                Role::Optional => {
+                    run.items.push(item.span.clone());
+                    run.optional += 1;
+                }
+                Role::Fails => {
+                    run.fails = true;
+                    worst = worst.max(core::mem::take(&mut run));
+                }
+            }
+        }
+        worst = worst.max(run);
+    }
+
+    let class = worst.class(parsed.anchored);
+    let items = if class == ComplexityClass::Linear {
+        Vec::new()
+    } else {
+        worst.items.into_iter().map(Span::pattern).collect()
+    };
+    Ok(Complexity { class, items })
+}
+
+/// The estimated worst-case complexity of matching a pattern, as returned by
+/// [`analyze_complexity`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Complexity {
+    /// How the work grows with the length of the input.
+    pub class: ComplexityClass,
+    /// The quantified items which the matcher backtracks through, in order.
+    /// This is empty if the class is [`ComplexityClass::Linear`].
+    pub items: Vec<Span>,
+}
+
+/// How the work of matching a pattern grows with the length n of the input.
+/// The classes are ordered from the cheapest to the most expensive.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum ComplexityClass {
+    /// The work is O(n).
+    Linear,
+    /// The work is O(n^degree), where the degree is at least 2.
+    Polynomial(u32),
+    /// The work grows exponentially with the number of the responsible
+    /// items, which are optional items that can match the same character.
+    /// Each of them doubles the number of ways to match, so the work can be
+    /// huge even for short inputs.
+    Exponential,
+}
+
+/// A run of items which can all match the same character.
+#[derive(Clone, Debug, Default)]
+struct Run {
+    /// The spans of the items which can match a varying number of the
+    /// characters.
+    items: Vec<Range<usize>>,
+    /// The number of items which can match any number of the characters.
+    unbounded: usize,
+    /// The number of items which can match one of the characters or none.
+    optional: usize,
+    /// Whether an item in or after the run can fail, so that the matcher
+    /// must backtrack through the run.
+    fails: bool,
+}
+
+impl Run {
+    /// Returns the complexity of backtracking through the run.
+    fn class(&self, anchored: bool) -> ComplexityClass {
+        if !self.fails {
+            // The first attempt succeeds
+            ComplexityClass::Linear
+        } else if self.optional >= MIN_EXPONENTIAL {
+            ComplexityClass::Exponential
+        } else {
+            // Each unbounded item tries up to n lengths, and an unanchored
+            // pattern is tried at up to n start positions
+            let degree = self.unbounded + usize::from(!anchored && self.unbounded != 0);
+            match u32::try_from(degree) {
+                Ok(0 | 1) => ComplexityClass::Linear,
+                Ok(degree) => ComplexityClass::Polynomial(degree),
+                Err(_) => ComplexityClass::Polynomial(u32::MAX),
+            }
+        }
+    }
+
+    /// Returns the more expensive of two runs.
+    fn max(self, other: Self) -> Self {
+        let key = |run: &Self| {
+            (
+                run.fails && run.optional >= MIN_EXPONENTIAL,
+                run.fails,
+                run.unbounded,
+                run.optional,
+            )
+        };
+        if key(&other) > key(&self) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// What an item does on an input which is a long run of a single character.
+enum Role {
+    /// It matches the empty string.
+    Empty,
+    /// It matches exactly one of the characters.
+    Fixed,
+    /// It can match any number of the characters, or does work for each of
+    /// them, and fails if `fails` is set.
+    Unbounded { fails: bool },
+    /// It matches one of the characters or none.
+    Optional,
+    /// It fails, which ends the run.
+    Fails,
+}
+
+/// Returns what an item does on an input which is a long run of `c`.
+fn role(kind: &ItemKind, c: u8) -> Role {
+    match kind {
+        ItemKind::Single { class, quantifier } => match (quantifier, class.matches(c)) {
+            (None, true) => Role::Fixed,
+            (Some(Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::Lazy), true) => {
+                Role::Unbounded { fails: false }
+            }
+            (Some(Quantifier::Optional), true) => Role::Optional,
+            (None | Some(Quantifier::OneOrMore), false) => Role::Fails,
+            (Some(_), false) => Role::Empty,
+        },
+        // A balance scans for its closing character, and a back-reference
+        // compares its capture, before they can fail
+        ItemKind::Balance { open, .. } if *open == c => Role::Unbounded { fails: true },
+        ItemKind::BackReference { .. } => Role::Unbounded { fails: true },
+        // Inside the run, a frontier sees the same character on both sides,
+        // so it never matches
+        ItemKind::Balance { .. } | ItemKind::EndAnchor | ItemKind::Frontier(_) => Role::Fails,
+        ItemKind::OpenCapture
+        | ItemKind::PositionCapture { .. }
+        | ItemKind::CloseCapture { .. } => Role::Empty,
+    }
+}
+
+/// The number of optional items in a run from which the work is counted as
+/// exponential.
+const MIN_EXPONENTIAL: usize = 8;
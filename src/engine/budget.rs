@@ -0,0 +1,78 @@
+use crate::{Error, MatchLimits, Result, Span};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Instant,
+};
+
+/// What the searches for matches of a single call may still do, which the
+/// matchers check as they go.
+pub(crate) struct Budget {
+    /// The maximum recursion depth of the equivalent recursive matcher.
+    pub max_depth: usize,
+    /// The maximum number of captures a match can open.
+    pub max_captures: usize,
+    /// The number of steps left.
+    steps: u64,
+    /// The flag which cancels the search once it is set.
+    cancel_flag: Option<Arc<AtomicBool>>,
+    /// The time by which the search must have finished.
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    pub fn new(limits: &MatchLimits) -> Self {
+        Self {
+            max_depth: limits.max_depth(),
+            max_captures: limits.max_captures(),
+            steps: limits.max_steps().unwrap_or(u64::MAX),
+            cancel_flag: limits.cancel_flag().cloned(),
+            deadline: limits.deadline(),
+        }
+    }
+
+    /// Replaces the step limit with `steps`.
+    pub fn with_steps(mut self, steps: u64) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Takes a step, or fails if there are none left. Every
+    /// [`CHECK_INTERVAL`] steps, also checks whether the search must stop.
+    /// `span` gives the part of the pattern being matched, for the error.
+    #[inline]
+    pub fn step(&mut self, span: impl FnOnce() -> Span) -> Result<()> {
+        if self.steps == 0 {
+            return Err(Error::StepLimitExceeded { span: span() });
+        }
+        self.steps -= 1;
+        if self.steps.is_multiple_of(CHECK_INTERVAL) {
+            return self.check(span);
+        }
+        Ok(())
+    }
+
+    /// Fails if the search was cancelled or its deadline has passed.
+    pub fn check(&self, span: impl FnOnce() -> Span) -> Result<()> {
+        if self
+            .cancel_flag
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::Relaxed))
+        {
+            return Err(Error::Cancelled { span: span() });
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(Error::DeadlineExceeded { span: span() });
+        }
+        Ok(())
+    }
+}
+
+/// The number of steps between checks of the cancellation flag and the
+/// deadline, which keeps the cost of reading the clock low.
+const CHECK_INTERVAL: u64 = 1024;
@@ -0,0 +1,45 @@
+/// A set of bytes stored as a 256-bit table, so that checking whether a byte
+/// is in the set is a single lookup.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    /// The set of no bytes.
+    pub const EMPTY: Self = Self([0; 4]);
+    /// The set of every byte.
+    pub const FULL: Self = Self([u64::MAX; 4]);
+
+    /// Adds the byte to the set.
+    #[inline]
+    pub const fn insert(&mut self, c: u8) {
+        self.0[(c >> 6) as usize] |= 1 << (c & 63);
+    }
+
+    /// Adds every byte from `first` to `last`, inclusive, to the set.
+    pub fn insert_range(&mut self, first: u8, last: u8) {
+        for c in first..=last {
+            self.insert(c);
+        }
+    }
+
+    /// Returns the set of the bytes which are in either set.
+    pub fn union(self, other: Self) -> Self {
+        Self(core::array::from_fn(|i| self.0[i] | other.0[i]))
+    }
+
+    /// Returns the set of the bytes which are not in this set.
+    pub fn complement(self) -> Self {
+        Self(self.0.map(|word| !word))
+    }
+
+    /// Checks whether the byte is in the set.
+    #[inline]
+    pub fn contains(&self, c: u8) -> bool {
+        self.0[usize::from(c >> 6)] & (1 << (c & 63)) != 0
+    }
+
+    /// Checks whether every byte is in the set.
+    pub fn is_full(&self) -> bool {
+        *self == Self::FULL
+    }
+}
@@ -0,0 +1,259 @@
+use super::{ByteSet, TwoWay};
+use crate::ast::{Item, ItemKind, Quantifier, Single};
+
+/// A pattern compiled into instructions for the matching VM.
+#[derive(Debug)]
+pub(crate) struct Program {
+    /// The instructions, ending with [`Op::Match`] or [`Op::Error`].
+    pub insts: Vec<Inst>,
+    /// Whether any instruction is a [`Op::BackReference`], which is the only
+    /// instruction whose result depends on how the matcher reached it.
+    pub back_references: bool,
+    /// Whether an automaton can match every instruction, which rules out
+    /// balances, back-references and syntax errors.
+    pub regular: bool,
+    /// Whether the pattern ends with an end anchor, so that every match ends
+    /// at the end of the input.
+    pub end_anchored: bool,
+    /// The literal string which every match of an end anchored pattern ends
+    /// with, which may be empty.
+    pub suffix: Box<[u8]>,
+    /// The minimum length of a match.
+    pub min_len: usize,
+    /// The maximum length of a match, if it is bounded.
+    pub max_len: Option<usize>,
+    /// The longest literal string which every match contains, if there is
+    /// one.
+    pub required: Option<Required>,
+}
+
+/// A literal string which every match of a [`Program`] contains.
+#[derive(Debug)]
+pub(crate) struct Required {
+    /// The searcher for the literal string.
+    pub literal: TwoWay,
+    /// The minimum length of a match before the literal string.
+    pub offset: usize,
+}
+
+/// A single instruction of a [`Program`].
+#[derive(Debug)]
+pub(crate) struct Inst {
+    /// The operation to run.
+    pub op: Op,
+    /// The index of the first pattern item which compiled to this
+    /// instruction, for error spans.
+    pub item: usize,
+}
+
+/// An operation of the matching VM.
+#[derive(Debug)]
+pub(crate) enum Op {
+    /// Matches a literal string.
+    Literal(Box<[u8]>),
+    /// Matches a single byte in the set.
+    Set(ByteSet),
+    /// Matches as many bytes in the set as possible, but at least `min`, and
+    /// then gives them back one at a time until the rest of the pattern
+    /// matches.
+    Greedy { set: ByteSet, min: usize },
+    /// Matches as few bytes in the set as possible until the rest of the
+    /// pattern matches.
+    Lazy(ByteSet),
+    /// Matches a single byte in the set if the rest of the pattern still
+    /// matches after it, or nothing otherwise.
+    Optional(ByteSet),
+    /// Starts a substring capture group at the next level.
+    Open,
+    /// Captures the current position at the next level.
+    Position,
+    /// Finishes the substring capture group at the given level.
+    Close { level: usize },
+    /// Matches a balanced string `%bxy`.
+    Balance { open: u8, close: u8 },
+    /// Matches the transition from a byte not in the set to a byte in it.
+    Frontier(ByteSet),
+    /// Matches the string captured by the finished group at the given level.
+    BackReference { level: usize },
+    /// Matches only at the end of the input.
+    AssertEnd,
+    /// Ends the pattern successfully.
+    Match,
+    /// Ends the pattern at its first syntax error, which is reported once it
+    /// is reached.
+    Error,
+}
+
+impl Program {
+    /// Compiles the items of a parsed pattern. If the pattern has a syntax
+    /// error after the items, `has_error` is set.
+    pub fn new(items: &[Item], has_error: bool) -> Self {
+        let mut insts = Vec::with_capacity(items.len() + 1);
+        // The current run of literal characters, which are joined into a
+        // single instruction, and the index of its first item
+        let mut literal = Vec::new();
+        let mut literal_item = 0;
+
+        for (index, item) in items.iter().enumerate() {
+            let op = match &item.kind {
+                ItemKind::Single {
+                    class: Single::Literal(c),
+                    quantifier: None,
+                } => {
+                    if literal.is_empty() {
+                        literal_item = index;
+                    }
+                    literal.push(*c);
+                    continue;
+                }
+                ItemKind::Single { class, quantifier } => {
+                    let bytes = class.bytes();
+                    match quantifier {
+                        None => Op::Set(bytes),
+                        Some(Quantifier::ZeroOrMore) => Op::Greedy { set: bytes, min: 0 },
+                        Some(Quantifier::OneOrMore) => Op::Greedy { set: bytes, min: 1 },
+                        Some(Quantifier::Lazy) => Op::Lazy(bytes),
+                        Some(Quantifier::Optional) => Op::Optional(bytes),
+                    }
+                }
+                ItemKind::OpenCapture => Op::Open,
+                ItemKind::PositionCapture { .. } => Op::Position,
+                ItemKind::CloseCapture { level } => Op::Close { level: *level },
+                ItemKind::Balance { open, close } => Op::Balance {
+                    open: *open,
+                    close: *close,
+                },
+                ItemKind::Frontier(set) => Op::Frontier(set.bytes()),
+                ItemKind::BackReference { level } => Op::BackReference { level: *level },
+                ItemKind::EndAnchor => Op::AssertEnd,
+            };
+            flush_literal(&mut insts, &mut literal, literal_item);
+            insts.push(Inst { op, item: index });
+        }
+
+        flush_literal(&mut insts, &mut literal, literal_item);
+        insts.push(Inst {
+            op: if has_error { Op::Error } else { Op::Match },
+            item: items.len(),
+        });
+
+        let back_references = insts
+            .iter()
+            .any(|inst| matches!(inst.op, Op::BackReference { .. }));
+        let regular = !insts.iter().any(|inst| {
+            matches!(
+                inst.op,
+                Op::Balance { .. } | Op::BackReference { .. } | Op::Error
+            )
+        });
+        let end_anchored = matches!(
+            insts.as_slice(),
+            [
+                ..,
+                Inst {
+                    op: Op::AssertEnd,
+                    ..
+                },
+                Inst { op: Op::Match, .. }
+            ]
+        );
+        let suffix = if end_anchored {
+            suffix(&insts)
+        } else {
+            <_>::default()
+        };
+        let min_len = insts.iter().map(|inst| min_len(&inst.op)).sum();
+        let max_len = max_len(&insts);
+        let required = required(&insts);
+
+        Self {
+            insts,
+            back_references,
+            regular,
+            end_anchored,
+            suffix,
+            min_len,
+            max_len,
+            required,
+        }
+    }
+}
+
+/// Returns the literal string which the instructions end with before the end
+/// anchor, skipping over captures, which do not consume any input.
+fn suffix(insts: &[Inst]) -> Box<[u8]> {
+    for inst in insts.iter().rev().skip(2) {
+        match &inst.op {
+            Op::Open | Op::Position | Op::Close { .. } => {}
+            Op::Literal(literal) => return literal.clone(),
+            _ => break,
+        }
+    }
+    <_>::default()
+}
+
+/// Returns the minimum number of bytes an operation matches.
+fn min_len(op: &Op) -> usize {
+    match op {
+        Op::Literal(literal) => literal.len(),
+        Op::Set(_) => 1,
+        Op::Greedy { min, .. } => *min,
+        Op::Balance { .. } => 2,
+        Op::Lazy(_)
+        | Op::Optional(_)
+        | Op::Open
+        | Op::Position
+        | Op::Close { .. }
+        | Op::Frontier(_)
+        | Op::BackReference { .. }
+        | Op::AssertEnd
+        | Op::Match
+        | Op::Error => 0,
+    }
+}
+
+/// Returns the longest literal string of the instructions, which every match
+/// contains since a pattern has no alternatives.
+fn required(insts: &[Inst]) -> Option<Required> {
+    let mut offset = 0;
+    let mut required: Option<(&[u8], usize)> = None;
+    for inst in insts {
+        if let Op::Literal(literal) = &inst.op
+            && required.is_none_or(|(longest, _)| literal.len() > longest.len())
+        {
+            required = Some((literal, offset));
+        }
+        offset += min_len(&inst.op);
+    }
+    required.map(|(literal, offset)| Required {
+        literal: TwoWay::new(literal.into()),
+        offset,
+    })
+}
+
+/// Returns the maximum length of a match of the instructions, or `None` if
+/// it is not bounded.
+fn max_len(insts: &[Inst]) -> Option<usize> {
+    insts.iter().try_fold(0, |len, inst| match &inst.op {
+        Op::Literal(literal) => Some(len + literal.len()),
+        Op::Set(_) | Op::Optional(_) => Some(len + 1),
+        Op::Greedy { .. } | Op::Lazy(_) | Op::Balance { .. } | Op::BackReference { .. } => None,
+        Op::Open
+        | Op::Position
+        | Op::Close { .. }
+        | Op::Frontier(_)
+        | Op::AssertEnd
+        | Op::Match
+        | Op::Error => Some(len),
+    })
+}
+
+/// Adds an instruction for a run of literal characters, if there is one.
+fn flush_literal(insts: &mut Vec<Inst>, literal: &mut Vec<u8>, item: usize) {
+    if !literal.is_empty() {
+        insts.push(Inst {
+            op: Op::Literal(core::mem::take(literal).into_boxed_slice()),
+            item,
+        });
+    }
+}
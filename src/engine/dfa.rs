@@ -0,0 +1,517 @@
+use super::{
+    Automata, Budget, ByteSet, Prefilter,
+    compile::Program,
+    pike::{Nfa, NfaInst},
+};
+use crate::{Error, Span, ast::Parsed};
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::Range,
+    sync::{Mutex, MutexGuard, TryLockError},
+};
+
+/// A deterministic automaton, built lazily from an [`Nfa`] one state at a
+/// time while matching. It only finds where a match is, without tracking
+/// captures, but visits each byte of the input once.
+///
+/// The states are cached between matches, up to a bound. If the cache fills
+/// up too often during a search, or another thread is using it, the search
+/// gives up and must be run by another engine.
+pub(crate) struct Dfa {
+    /// The automaton for the reversed pattern, which finds where a match
+    /// starts from where it ends.
+    reverse: Nfa,
+    /// The distinct sets of the frontier items. Each state records which of
+    /// them contain the byte before it.
+    frontiers: Vec<ByteSet>,
+    /// The states of the forward and the reverse automata.
+    cache: Mutex<(Cache, Cache)>,
+}
+
+/// The cache of the DFA filled up too often during a search, or another
+/// thread is using it.
+pub(crate) struct GaveUp;
+
+/// Why the DFA could not finish a search.
+pub(crate) enum Stop {
+    /// The search must be run by another engine instead.
+    GaveUp,
+    /// The search failed.
+    Error(Error),
+}
+
+impl From<GaveUp> for Stop {
+    fn from(GaveUp: GaveUp) -> Self {
+        Self::GaveUp
+    }
+}
+
+/// The direction in which an automaton reads the input.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Direction {
+    /// From the start of a match to its end, preferring the match Lua finds.
+    Forward,
+    /// From the end of a match to its start, finding the earliest start.
+    Reverse,
+}
+
+/// The states of an automaton which have been built so far.
+#[derive(Default)]
+struct Cache {
+    /// The key of each state by id. This is the mask of the frontiers which
+    /// contain the byte before the state as two words, then whether new
+    /// threads are still started, then the instructions of the threads in
+    /// priority order, before following those which do not consume input.
+    states: Vec<Box<[u32]>>,
+    /// The id of each state by its key.
+    ids: HashMap<Box<[u32]>, u32>,
+    /// The transitions of each state on each byte and on the end of the
+    /// input, or [`UNKNOWN`]. A transition is the id of the next state
+    /// shifted left by one, with the lowest bit set if a match ends before
+    /// the byte.
+    transitions: Vec<u32>,
+    /// The number of times the cache was cleared during the current search.
+    clears: usize,
+    /// The instructions already followed while building a transition.
+    seen: Vec<bool>,
+    /// The instructions still to follow while building a transition.
+    stack: Vec<usize>,
+    /// The key of the state being built.
+    key: Vec<u32>,
+}
+
+impl Dfa {
+    /// Prepares a lazy automaton for the pattern compiled into `nfa`. Returns
+    /// `None` if the pattern has too many frontiers to track.
+    pub fn new(nfa: &Nfa, program: &Program) -> Option<Self> {
+        let mut frontiers = Vec::new();
+        for inst in &nfa.insts {
+            if let NfaInst::Frontier(set) = inst
+                && !frontiers.contains(set)
+            {
+                frontiers.push(*set);
+            }
+        }
+        if frontiers.len() > 64 {
+            return None;
+        }
+
+        Some(Self {
+            reverse: Nfa::reverse(program)?,
+            frontiers,
+            cache: Mutex::default(),
+        })
+    }
+
+    /// Finds the range of the first match of the pattern in the input string,
+    /// starting the search at `start_index` (0-based).
+    pub fn find(
+        &self,
+        input: &[u8],
+        parsed: &Parsed,
+        start_index: usize,
+        budget: &mut Budget,
+    ) -> Result<Option<Range<usize>>, Stop> {
+        let mut cache = self.lock()?;
+        let (forward, reverse) = &mut *cache;
+        if parsed.program.end_anchored && !parsed.anchored {
+            // Every match ends at the end of the input, so the reverse
+            // automaton can find where the first one starts from there
+            // without reading the rest of the input
+            let end = input.len();
+            return Ok(self
+                .find_start(reverse, input, parsed, start_index, end, budget)?
+                .map(|start| start..end));
+        }
+        let Some(end) = self.find_end(forward, input, parsed, start_index, false, budget)? else {
+            return Ok(None);
+        };
+        if parsed.anchored {
+            return Ok(Some(start_index..end));
+        }
+        Ok(self
+            .find_start(reverse, input, parsed, start_index, end, budget)?
+            .map(|start| start..end))
+    }
+
+    /// Checks whether the pattern matches anywhere in the input string,
+    /// starting the search at `start_index` (0-based).
+    pub fn is_match(
+        &self,
+        input: &[u8],
+        parsed: &Parsed,
+        start_index: usize,
+        budget: &mut Budget,
+    ) -> Result<bool, Stop> {
+        let mut cache = self.lock()?;
+        let (forward, reverse) = &mut *cache;
+        if parsed.program.end_anchored && !parsed.anchored {
+            return Ok(self
+                .find_start(reverse, input, parsed, start_index, input.len(), budget)?
+                .is_some());
+        }
+        Ok(self
+            .find_end(forward, input, parsed, start_index, true, budget)?
+            .is_some())
+    }
+
+    /// Takes the cache for a search, unless another thread is using it.
+    fn lock(&self) -> Result<MutexGuard<'_, (Cache, Cache)>, GaveUp> {
+        let mut cache = match self.cache.try_lock() {
+            Ok(cache) => cache,
+            Err(TryLockError::Poisoned(poisoned)) => {
+                // The cache may have been left half updated
+                let mut cache = poisoned.into_inner();
+                *cache = <_>::default();
+                cache
+            }
+            Err(TryLockError::WouldBlock) => return Err(GaveUp),
+        };
+        cache.0.clears = 0;
+        cache.1.clears = 0;
+        Ok(cache)
+    }
+
+    /// Runs the forward automaton. Returns where the first match ends, or
+    /// where any match ends first if `earliest` is set. Each byte read takes
+    /// a step of `budget`.
+    fn find_end(
+        &self,
+        cache: &mut Cache,
+        input: &[u8],
+        parsed: &Parsed,
+        start_index: usize,
+        earliest: bool,
+        budget: &mut Budget,
+    ) -> Result<Option<usize>, Stop> {
+        let Some(Automata { nfa, .. }) = parsed.automata() else {
+            unreachable!("only built for patterns with automata");
+        };
+        if start_index > input.len() {
+            return Ok(None);
+        }
+        let skips = !parsed.anchored && !matches!(parsed.prefilter, Prefilter::None);
+
+        let mut pos = start_index;
+        let mut state = self.start(cache, byte_before(input, pos), !parsed.anchored)?;
+        let mut end = None;
+        loop {
+            if skips && cache.states[state as usize].len() == HEADER {
+                // No thread is running, so skip straight to the next
+                // position where a match can start
+                let Some(next) = parsed.prefilter.find(input, pos) else {
+                    break;
+                };
+                if next != pos {
+                    pos = next;
+                    state = self.start(cache, byte_before(input, pos), true)?;
+                }
+            }
+
+            take_step(budget, parsed)?;
+            let unit = input.get(pos).map_or(END, |&c| usize::from(c));
+            let transition = self.next(nfa, cache, Direction::Forward, state, unit)?;
+            if transition & 1 != 0 {
+                end = Some(pos);
+                if earliest {
+                    break;
+                }
+            }
+            state = transition >> 1;
+            if state == DEAD || pos == input.len() {
+                break;
+            }
+            pos += 1;
+        }
+
+        Ok(end)
+    }
+
+    /// Runs the reverse automaton from the end of the first match back to
+    /// `start_index`. Returns where the match starts, which is the earliest
+    /// position that a match ending there can start from.
+    fn find_start(
+        &self,
+        cache: &mut Cache,
+        input: &[u8],
+        parsed: &Parsed,
+        start_index: usize,
+        end: usize,
+        budget: &mut Budget,
+    ) -> Result<Option<usize>, Stop> {
+        if start_index > end {
+            return Ok(None);
+        }
+        let mut pos = end;
+        let after = input.get(end).copied().unwrap_or(b'\0');
+        let mut state = self.start(cache, after, false)?;
+        let mut start = None;
+        loop {
+            take_step(budget, parsed)?;
+            let unit = usize::from(byte_before(input, pos));
+            let transition = self.next(&self.reverse, cache, Direction::Reverse, state, unit)?;
+            if transition & 1 != 0 {
+                start = Some(pos);
+            }
+            state = transition >> 1;
+            if state == DEAD || pos == start_index {
+                break;
+            }
+            pos -= 1;
+        }
+
+        Ok(start)
+    }
+
+    /// Returns the state at which an automaton starts next to the given
+    /// byte, which is the byte before the start for the forward automaton and
+    /// the byte after it for the reverse one. If `searching` is set, a new
+    /// thread is started at every position until a match is found, and
+    /// otherwise a single thread starts here.
+    fn start(&self, cache: &mut Cache, next_to: u8, searching: bool) -> Result<u32, GaveUp> {
+        cache.key.clear();
+        cache.key.extend(header(self.mask(next_to), searching));
+        if !searching {
+            cache.key.push(0);
+        }
+        cache.insert_key()
+    }
+
+    /// Returns the transition of a state on a byte, or on the end of the
+    /// input if `unit` is [`END`], building it if it is not cached yet.
+    fn next(
+        &self,
+        nfa: &Nfa,
+        cache: &mut Cache,
+        direction: Direction,
+        state: u32,
+        unit: usize,
+    ) -> Result<u32, GaveUp> {
+        let index = state as usize * STRIDE + unit;
+        let transition = cache.transitions[index];
+        if transition != UNKNOWN {
+            return Ok(transition);
+        }
+
+        let matched = self.build_key(nfa, cache, direction, state, unit);
+        let clears = cache.clears;
+        let next = cache.insert_key()?;
+        let transition = next << 1 | u32::from(matched);
+        // The state is gone if the cache had to be cleared for the next one
+        if cache.clears == clears {
+            cache.transitions[index] = transition;
+        }
+        Ok(transition)
+    }
+
+    /// Follows the threads of a state over a byte, or the end of the input,
+    /// and leaves the key of the next state in `cache.key`. Returns whether a
+    /// match ends before the byte.
+    fn build_key(
+        &self,
+        nfa: &Nfa,
+        cache: &mut Cache,
+        direction: Direction,
+        state: u32,
+        unit: usize,
+    ) -> bool {
+        let Cache {
+            states,
+            seen,
+            stack,
+            key,
+            ..
+        } = cache;
+        let state = &states[state as usize];
+        let before = u64::from(state[0]) | u64::from(state[1]) << 32;
+        let searching = state[2] != 0;
+        let at_end = unit == END;
+        // Lua manual: “The beginning and end of the subject are handled as if
+        // they were the character '\0'.”
+        let byte = u8::try_from(unit).unwrap_or(b'\0');
+
+        seen.clear();
+        seen.resize(nfa.insts.len(), false);
+        key.clear();
+        key.extend([0; HEADER]);
+        let mut matched = false;
+
+        let threads = state[HEADER..].iter().map(|&pc| pc as usize);
+        // A thread which starts here has a lower priority than the threads
+        // which started earlier
+        'threads: for pc in threads.chain(searching.then_some(0)) {
+            stack.push(pc);
+            while let Some(pc) = stack.pop() {
+                if core::mem::replace(&mut seen[pc], true) {
+                    continue;
+                }
+                match &nfa.insts[pc] {
+                    NfaInst::Byte(c) => {
+                        if !at_end && *c == byte {
+                            key.push(thread(pc + 1));
+                        }
+                    }
+                    NfaInst::Set(set) => {
+                        if !at_end && set.contains(byte) {
+                            key.push(thread(pc + 1));
+                        }
+                    }
+                    NfaInst::Split(first, second) => {
+                        stack.push(*second);
+                        stack.push(*first);
+                    }
+                    NfaInst::Jump(to) => stack.push(*to),
+                    NfaInst::Save(_) => stack.push(pc + 1),
+                    NfaInst::Frontier(set) => {
+                        let first = before & self.frontier_bit(set) != 0;
+                        let last = set.contains(byte);
+                        let passes = match direction {
+                            Direction::Forward => !first && last,
+                            Direction::Reverse => first && !last,
+                        };
+                        if passes {
+                            stack.push(pc + 1);
+                        }
+                    }
+                    NfaInst::AssertEnd => {
+                        if at_end {
+                            stack.push(pc + 1);
+                        }
+                    }
+                    NfaInst::Match => {
+                        matched = true;
+                        if direction == Direction::Forward {
+                            // Threads with a lower priority cannot win anymore
+                            stack.clear();
+                            break 'threads;
+                        }
+                    }
+                }
+            }
+        }
+
+        let searching = searching && !matched;
+        if key.len() == HEADER && !searching {
+            key.copy_from_slice(&header(0, false));
+        } else {
+            key[..HEADER].copy_from_slice(&header(self.mask(byte), searching));
+        }
+        matched
+    }
+
+    /// Returns the mask of the frontiers which contain the byte.
+    fn mask(&self, c: u8) -> u64 {
+        self.frontiers
+            .iter()
+            .enumerate()
+            .filter(|(_, set)| set.contains(c))
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
+    /// Returns the bit of the frontier set in a mask.
+    fn frontier_bit(&self, set: &ByteSet) -> u64 {
+        let Some(i) = self.frontiers.iter().position(|frontier| frontier == set) else {
+            unreachable!("every frontier set is collected");
+        };
+        1 << i
+    }
+}
+
+impl fmt::Debug for Dfa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dfa")
+            .field("reverse", &self.reverse)
+            .field("frontiers", &self.frontiers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cache {
+    /// Returns the id of the state with the key in `self.key`, adding it if
+    /// it is not cached yet.
+    fn insert_key(&mut self) -> Result<u32, GaveUp> {
+        if let Some(&id) = self.ids.get(self.key.as_slice()) {
+            return Ok(id);
+        }
+        if self.states.len() >= MAX_STATES {
+            self.clears += 1;
+            if self.clears > MAX_CLEARS {
+                return Err(GaveUp);
+            }
+            self.states.clear();
+            self.ids.clear();
+            self.transitions.clear();
+        }
+        if self.states.is_empty() {
+            self.insert(header(0, false).into());
+            if self.key.as_slice() == header(0, false) {
+                return Ok(DEAD);
+            }
+        }
+        Ok(self.insert(self.key.as_slice().into()))
+    }
+
+    /// Adds a state and returns its id.
+    fn insert(&mut self, key: Box<[u32]>) -> u32 {
+        // Clippy: There are at most `MAX_STATES` states
+        #[allow(clippy::cast_possible_truncation)]
+        let id = self.states.len() as u32;
+        self.ids.insert(key.clone(), id);
+        self.states.push(key);
+        self.transitions
+            .resize(self.transitions.len() + STRIDE, UNKNOWN);
+        id
+    }
+}
+
+/// Returns the start of a state key.
+// Clippy: The mask is split into its two halves on purpose
+#[allow(clippy::cast_possible_truncation)]
+fn header(mask: u64, searching: bool) -> [u32; HEADER] {
+    [mask as u32, (mask >> 32) as u32, u32::from(searching)]
+}
+
+/// Returns the key entry of a thread at the instruction.
+fn thread(pc: usize) -> u32 {
+    // Clippy: A pattern string long enough to compile to 2^32 instructions
+    // cannot be allocated
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        pc as u32
+    }
+}
+
+/// Returns the byte before a position, which is `'\0'` at the start.
+fn byte_before(input: &[u8], pos: usize) -> u8 {
+    pos.checked_sub(1).map_or(b'\0', |pos| input[pos])
+}
+
+/// Takes a step of `budget` for reading a byte.
+fn take_step(budget: &mut Budget, parsed: &Parsed) -> Result<(), Stop> {
+    budget
+        .step(|| Span::pattern(0..parsed.len))
+        .map_err(Stop::Error)
+}
+
+/// The length of the start of a state key, before its threads.
+const HEADER: usize = 3;
+
+/// The id of the state without threads, which never matches.
+const DEAD: u32 = 0;
+
+/// The transition unit of the end of the input.
+const END: usize = 256;
+
+/// The number of transitions of each state.
+const STRIDE: usize = 257;
+
+/// A transition which has not been built yet.
+const UNKNOWN: u32 = u32::MAX;
+
+/// The maximum number of states in a cache.
+const MAX_STATES: usize = 512;
+
+/// The number of times a cache can be cleared during a search before the
+/// search gives up.
+const MAX_CLEARS: usize = 8;
@@ -0,0 +1,420 @@
+use crate::{LUA_MAXCAPTURES, ast::Class};
+
+/// Checks whether the pattern string matches the input anywhere after
+/// `start_index`, by matching the pattern string directly like Lua does,
+/// without parsing or compiling it first. All of its state is kept in fixed
+/// size arrays, so nothing is allocated.
+///
+/// Returns `None` if it cannot decide, which the full matchers must then do.
+/// This is the case if the matcher reaches a syntax error or finishes a match
+/// with a capture group still open, which are reported as errors, or if it
+/// backtracks deeper or for longer than it has room for.
+pub fn has_direct_match(input: &[u8], pattern: &[u8], start_index: usize) -> Option<bool> {
+    let anchored = pattern.first() == Some(&b'^');
+    let mut matcher = Direct {
+        input,
+        pattern: &pattern[usize::from(anchored)..],
+        work: (input.len() + 1)
+            .saturating_mul(pattern.len() + 1)
+            .saturating_mul(WORK_PER_PAIR),
+        level: 0,
+        captures: [Slot::Position; LUA_MAXCAPTURES],
+        depth: 0,
+        stack: [Frame::Open; MAX_FRAMES],
+    };
+
+    for start in start_index..=input.len() {
+        if matcher.run(start)? {
+            return Some(true);
+        }
+        if anchored {
+            break;
+        }
+    }
+    Some(false)
+}
+
+/// A backtracking matcher which works on the pattern string itself.
+struct Direct<'a> {
+    /// The input string to match.
+    input: &'a [u8],
+    /// The pattern to match, without any leading anchor.
+    pattern: &'a [u8],
+    /// The number of steps left before the matcher gives up.
+    work: usize,
+    /// Number of capture groups.
+    level: usize,
+    /// Intermediate capture group states.
+    captures: [Slot; LUA_MAXCAPTURES],
+    /// The number of points to backtrack to.
+    depth: usize,
+    /// The points to backtrack to, innermost last.
+    stack: [Frame; MAX_FRAMES],
+}
+
+/// The state of a capture group.
+#[derive(Clone, Copy)]
+enum Slot {
+    /// A substring capture group which is waiting to be closed.
+    Pending { start: usize },
+    /// A finished substring capture group.
+    Range { start: usize, end: usize },
+    /// A current string position capture group.
+    Position,
+}
+
+/// A point on the backtracking stack, which mirrors those of the matching VM
+/// so that the stack is never shallower than the VM's.
+#[derive(Clone, Copy)]
+enum Frame {
+    /// A greedy repetition which matched `count` bytes from `s`, and can give
+    /// some of them back to retry the rest of the pattern from `next`.
+    Greedy {
+        next: usize,
+        s: usize,
+        count: usize,
+        min: usize,
+    },
+    /// A lazy repetition of the item at `p`, which can take the byte at `s`
+    /// to retry the rest of the pattern from `next`.
+    Lazy { p: usize, next: usize, s: usize },
+    /// An optional item which matched, and can instead match nothing at `s`
+    /// to retry the rest of the pattern from `next`.
+    Optional { next: usize, s: usize },
+    /// A capture group was started, and must be dropped when backtracking.
+    Open,
+    /// The capture group at the given level was finished, and must be
+    /// reopened when backtracking.
+    Close { level: usize },
+}
+
+/// What the matcher does after an item.
+enum Next {
+    /// It continues with the pattern and input positions.
+    Item(usize, usize),
+    /// It backtracks.
+    Fail,
+}
+
+impl Direct<'_> {
+    /// Matches the pattern with the input starting at position `s`. Returns
+    /// whether it matched, or `None` if the matcher cannot decide.
+    fn run(&mut self, mut s: usize) -> Option<bool> {
+        self.level = 0;
+        self.depth = 0;
+        let mut p = 0;
+        loop {
+            self.work = self.work.checked_sub(1)?;
+            if p == self.pattern.len() {
+                // A capture group can only still be open if the pattern
+                // never closes it, which is an error
+                return self.captures[..self.level]
+                    .iter()
+                    .all(|capture| !matches!(capture, Slot::Pending { .. }))
+                    .then_some(true);
+            }
+            match self.item(p, s)? {
+                Next::Item(next_p, next_s) => (p, s) = (next_p, next_s),
+                Next::Fail => {
+                    let Some(resume) = self.backtrack() else {
+                        return Some(false);
+                    };
+                    (p, s) = resume;
+                }
+            }
+        }
+    }
+
+    /// Matches the item at pattern position `p` with the input at position
+    /// `s`.
+    fn item(&mut self, p: usize, s: usize) -> Option<Next> {
+        match self.pattern[p] {
+            b'(' => {
+                if self.level == LUA_MAXCAPTURES {
+                    return None;
+                }
+                let (slot, next) = if self.pattern.get(p + 1) == Some(&b')') {
+                    (Slot::Position, p + 2)
+                } else {
+                    (Slot::Pending { start: s }, p + 1)
+                };
+                self.captures[self.level] = slot;
+                self.level += 1;
+                self.push(Frame::Open)?;
+                return Some(Next::Item(next, s));
+            }
+            b')' => {
+                let level = (0..self.level)
+                    .rev()
+                    .find(|&level| matches!(self.captures[level], Slot::Pending { .. }))?;
+                let Slot::Pending { start } = self.captures[level] else {
+                    unreachable!("found as a pending capture");
+                };
+                self.captures[level] = Slot::Range { start, end: s };
+                self.push(Frame::Close { level })?;
+                return Some(Next::Item(p + 1, s));
+            }
+            b'$' if p + 1 == self.pattern.len() => {
+                return Some(if s == self.input.len() {
+                    Next::Item(p + 1, s)
+                } else {
+                    Next::Fail
+                });
+            }
+            b'%' => match self.pattern.get(p + 1).copied() {
+                Some(b'b') => {
+                    let (&open, &close) = (self.pattern.get(p + 2)?, self.pattern.get(p + 3)?);
+                    return self.match_balance(p + 4, s, open, close);
+                }
+                Some(b'f') => {
+                    if self.pattern.get(p + 2) != Some(&b'[') {
+                        return None;
+                    }
+                    let end = self.class_end(p + 2)?;
+                    // Lua manual: “The beginning and end of the subject are
+                    // handled as if they were the character '\0'.”
+                    let first = if s == 0 { b'\0' } else { self.input[s - 1] };
+                    let last = self.input.get(s).copied().unwrap_or(b'\0');
+                    return Some(
+                        if !self.set_matches(p + 2, end - 1, first)
+                            && self.set_matches(p + 2, end - 1, last)
+                        {
+                            Next::Item(end, s)
+                        } else {
+                            Next::Fail
+                        },
+                    );
+                }
+                Some(digit @ b'0'..=b'9') => {
+                    let level = usize::from(digit.checked_sub(b'1')?);
+                    if level >= self.level {
+                        return None;
+                    }
+                    let Slot::Range { start, end } = self.captures[level] else {
+                        return None;
+                    };
+                    let next = s + (end - start);
+                    return Some(
+                        if self.input.get(s..next) == Some(&self.input[start..end]) {
+                            Next::Item(p + 2, next)
+                        } else {
+                            Next::Fail
+                        },
+                    );
+                }
+                _ => {
+                    // This is actually a single character class, so handle it
+                    // below.
+                }
+            },
+            _ => {
+                // This is actually a normal character, so handle it below.
+            }
+        }
+
+        let end = self.class_end(p)?;
+        let matches = self.single_matches(s, p, end);
+        Some(match self.pattern.get(end) {
+            Some(b'?') => {
+                if matches {
+                    self.push(Frame::Optional { next: end + 1, s })?;
+                    Next::Item(end + 1, s + 1)
+                } else {
+                    Next::Item(end + 1, s)
+                }
+            }
+            Some(quantifier @ (b'*' | b'+')) => {
+                let min = usize::from(*quantifier == b'+');
+                let mut count = 0;
+                while self.single_matches(s + count, p, end) {
+                    self.work = self.work.checked_sub(1)?;
+                    count += 1;
+                }
+                if count < min {
+                    Next::Fail
+                } else {
+                    // If nothing matched, there is nothing to give back
+                    if count != 0 {
+                        self.push(Frame::Greedy {
+                            next: end + 1,
+                            s,
+                            count,
+                            min,
+                        })?;
+                    }
+                    Next::Item(end + 1, s + count)
+                }
+            }
+            Some(b'-') => {
+                if matches {
+                    self.push(Frame::Lazy {
+                        p,
+                        next: end + 1,
+                        s,
+                    })?;
+                }
+                Next::Item(end + 1, s)
+            }
+            _ if matches => Next::Item(end, s + 1),
+            _ => Next::Fail,
+        })
+    }
+
+    /// Adds a backtracking point, or gives up if there is no room for it.
+    fn push(&mut self, frame: Frame) -> Option<()> {
+        *self.stack.get_mut(self.depth)? = frame;
+        self.depth += 1;
+        Some(())
+    }
+
+    /// Unwinds the stack to the innermost backtracking point which has
+    /// another alternative, and returns the pattern and input position to
+    /// resume from. Returns `None` if there are no alternatives left.
+    fn backtrack(&mut self) -> Option<(usize, usize)> {
+        while self.depth > 0 {
+            match &mut self.stack[self.depth - 1] {
+                Frame::Greedy {
+                    next,
+                    s,
+                    count,
+                    min,
+                } => {
+                    if *count > *min {
+                        *count -= 1;
+                        return Some((*next, *s + *count));
+                    }
+                }
+                Frame::Lazy { p, next, s } => {
+                    let (p, next, s) = (*p, *next, *s);
+                    if self.single_matches(s, p, next - 1) {
+                        self.stack[self.depth - 1] = Frame::Lazy { p, next, s: s + 1 };
+                        return Some((next, s + 1));
+                    }
+                }
+                Frame::Optional { next, s } => {
+                    let resume = (*next, *s);
+                    self.depth -= 1;
+                    return Some(resume);
+                }
+                Frame::Open => self.level -= 1,
+                Frame::Close { level } => {
+                    if let Slot::Range { start, .. } = self.captures[*level] {
+                        self.captures[*level] = Slot::Pending { start };
+                    }
+                }
+            }
+            self.depth -= 1;
+        }
+        None
+    }
+
+    /// Matches a balance item `%bxy` at input position `s`, which continues
+    /// with the item at pattern position `next` if successful. Returns `None`
+    /// if the matcher runs out of work.
+    fn match_balance(&mut self, next: usize, s: usize, open: u8, close: u8) -> Option<Next> {
+        if self.input.get(s) != Some(&open) {
+            return Some(Next::Fail);
+        }
+
+        let mut count = 1;
+        for s in s + 1..self.input.len() {
+            self.work = self.work.checked_sub(1)?;
+            if self.input[s] == close {
+                count -= 1;
+                if count == 0 {
+                    return Some(Next::Item(next, s + 1));
+                }
+            } else if self.input[s] == open {
+                count += 1;
+            }
+        }
+        Some(Next::Fail)
+    }
+
+    /// Finds the end of the single character class at pattern position `p`.
+    /// Returns the position after it, or `None` if it is malformed.
+    fn class_end(&self, mut p: usize) -> Option<usize> {
+        let c = self.pattern[p];
+        p += 1;
+        match c {
+            b'%' => (p < self.pattern.len()).then_some(p + 1),
+            b'[' => {
+                if self.pattern.get(p) == Some(&b'^') {
+                    p += 1;
+                }
+                // The first character of a set is never its end, even if
+                // it is a bracket
+                loop {
+                    if p == self.pattern.len() {
+                        return None;
+                    }
+                    p += 1;
+                    if self.pattern[p - 1] == b'%' && p < self.pattern.len() {
+                        p += 1;
+                    }
+                    if self.pattern.get(p) == Some(&b']') {
+                        return Some(p + 1);
+                    }
+                }
+            }
+            _ => Some(p),
+        }
+    }
+
+    /// Checks whether the input at position `s` matches the single character
+    /// class from pattern position `p` to `end`.
+    fn single_matches(&self, s: usize, p: usize, end: usize) -> bool {
+        let Some(&c) = self.input.get(s) else {
+            return false;
+        };
+        match self.pattern[p] {
+            b'.' => true,
+            b'%' => escape_matches(self.pattern[p + 1], c),
+            b'[' => self.set_matches(p, end - 1, c),
+            literal => literal == c,
+        }
+    }
+
+    /// Checks whether the character matches the set between the brackets at
+    /// pattern positions `p` and `end`.
+    fn set_matches(&self, mut p: usize, end: usize, c: u8) -> bool {
+        let negated = self.pattern[p + 1] == b'^';
+        p += 1 + usize::from(negated);
+        while p < end {
+            if self.pattern[p] == b'%' {
+                // %w
+                if escape_matches(self.pattern[p + 1], c) {
+                    return !negated;
+                }
+                p += 2;
+            } else if self.pattern[p + 1] == b'-' && p + 2 < end {
+                // [a-z]
+                if self.pattern[p] <= c && c <= self.pattern[p + 2] {
+                    return !negated;
+                }
+                p += 3;
+            } else {
+                // Literal character
+                if self.pattern[p] == c {
+                    return !negated;
+                }
+                p += 1;
+            }
+        }
+        negated
+    }
+}
+
+/// Checks whether the character matches the escaped character class `%x`,
+/// which matches itself if it is not a class.
+fn escape_matches(escape: u8, c: u8) -> bool {
+    Class::from_byte(escape).map_or(escape == c, |class| class.matches(c))
+}
+
+/// The number of backtracking points the matcher has room for, which is well
+/// below the default depth limit of the other matchers.
+const MAX_FRAMES: usize = 64;
+
+/// The number of steps which the matcher can take for each pair of pattern
+/// and input position before it gives up.
+const WORK_PER_PAIR: usize = 4;
@@ -0,0 +1,88 @@
+use core::ops::{Deref, DerefMut};
+
+/// A vector which keeps up to `N` elements inline and only moves them onto
+/// the heap once it grows past that, so that small matches do not allocate.
+pub(crate) struct InlineVec<T, const N: usize> {
+    /// The elements, while there are no more than `N` of them.
+    inline: [T; N],
+    /// The elements, once there have been more than `N` of them. If this is
+    /// empty, the elements are inline.
+    heap: Vec<T>,
+    /// The number of elements.
+    len: usize,
+}
+
+impl<T: Copy + Default, const N: usize> InlineVec<T, N> {
+    pub fn new() -> Self {
+        Self {
+            inline: [T::default(); N],
+            heap: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Removes all elements, keeping any heap allocation for reuse.
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.len = 0;
+    }
+
+    /// Appends an element.
+    pub fn push(&mut self, value: T) {
+        if self.heap.is_empty() {
+            if let Some(slot) = self.inline.get_mut(self.len) {
+                *slot = value;
+                self.len += 1;
+                return;
+            }
+            self.heap.extend_from_slice(&self.inline);
+        }
+        self.heap.push(value);
+        self.len += 1;
+    }
+
+    /// Removes the last element.
+    pub fn pop(&mut self) -> Option<T> {
+        let value = if self.heap.is_empty() {
+            *self.inline[..self.len].last()?
+        } else {
+            self.heap.pop()?
+        };
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Replaces the contents with `len` copies of `value`.
+    pub fn fill(&mut self, len: usize, value: T) {
+        self.clear();
+        if len <= N {
+            self.inline[..len].fill(value);
+            self.len = len;
+        } else {
+            self.heap.resize(len, value);
+            self.len = len;
+        }
+    }
+}
+
+impl<T, const N: usize> Deref for InlineVec<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        if self.heap.is_empty() {
+            &self.inline[..self.len]
+        } else {
+            &self.heap
+        }
+    }
+}
+
+impl<T, const N: usize> DerefMut for InlineVec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        if self.heap.is_empty() {
+            &mut self.inline[..self.len]
+        } else {
+            &mut self.heap
+        }
+    }
+}
@@ -0,0 +1,545 @@
+use super::{
+    Error, MatchLimits, Result, Span,
+    ast::Parsed,
+    lua::{Capture, Indexing},
+};
+use dfa::Stop;
+use std::ops::Range;
+use vm::{Vm, VmScratch};
+
+mod budget;
+mod byte_set;
+mod compile;
+mod dfa;
+mod direct;
+mod inline_vec;
+mod pike;
+mod prefilter;
+mod two_way;
+mod vm;
+
+pub(crate) use self::{
+    budget::Budget,
+    byte_set::ByteSet,
+    compile::Program,
+    dfa::Dfa,
+    direct::has_direct_match,
+    pike::{Nfa, PikeScratch},
+    prefilter::Prefilter,
+    two_way::TwoWay,
+};
+
+/// The automata of a regular pattern.
+#[derive(Debug)]
+pub(crate) struct Automata {
+    /// The pattern compiled into an automaton. If it can backtrack, it is
+    /// matched in linear time by the automaton instead of by the VM.
+    pub nfa: Nfa,
+    /// The lazy deterministic automaton which finds where matches are,
+    /// before their captures are found, if it is worth building.
+    pub dfa: Option<Dfa>,
+}
+
+impl Automata {
+    /// Compiles the program of a regular pattern into automata.
+    pub fn new(program: &Program, literal: bool) -> Self {
+        let Some(nfa) = Nfa::new(program) else {
+            unreachable!("only called for regular patterns");
+        };
+        // The prefilter alone finds the matches of a literal pattern
+        let dfa = if literal {
+            None
+        } else {
+            Dfa::new(&nfa, program)
+        };
+        Self { nfa, dfa }
+    }
+}
+
+/// A capture group.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum CaptureRange {
+    /// A substring capture group.
+    Range(Range<usize>),
+    /// A current string position capture group.
+    Position(usize),
+}
+
+impl CaptureRange {
+    #[must_use]
+    /// Returns the range of the capture group in the input string. The range
+    /// of a position capture group is empty.
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            CaptureRange::Range(range) => range.clone(),
+            CaptureRange::Position(at) => *at..*at,
+        }
+    }
+
+    pub fn into_capture(self, text: &[u8], indexing: Indexing) -> Capture<'_> {
+        match self {
+            CaptureRange::Range(range) => Capture::Str(&text[range]),
+            CaptureRange::Position(at) => Capture::Position(indexing.position(at)),
+        }
+    }
+}
+
+impl Default for CaptureRange {
+    fn default() -> Self {
+        Self::Range(<_>::default())
+    }
+}
+
+/// The ranged indexes of a matched pattern. These are always 0-indexed.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct MatchRanges {
+    /// The full range of the matched pattern.
+    pub full_match: Range<usize>,
+    /// The ranges of each captured group. If a group did not capture anything,
+    /// the range will be empty.
+    pub captures: Vec<CaptureRange>,
+}
+
+/// Tries to find the first match of the pattern in the input string,
+/// starting the search at `start_index` (0-based), within what is left of
+/// `budget`, which can be shared by the searches of a single call.
+/// Returns the range of the full match and the ranges of captures if successful.
+pub fn find_first_match(
+    input: &[u8],
+    parsed: &Parsed,
+    start_index: usize,
+    budget: &mut Budget,
+) -> Result<Option<MatchRanges>> {
+    let mut captures = Vec::new();
+    let full_match = find_into(
+        input,
+        parsed,
+        start_index,
+        budget,
+        &mut Scratch::new(),
+        &mut captures,
+    )?;
+    Ok(full_match.map(|full_match| MatchRanges {
+        full_match,
+        captures,
+    }))
+}
+
+/// Like [`find_first_match`], but reuses the memory in `scratch` and writes
+/// the ranges of the captures into `captures`, so that nothing is allocated
+/// once they have grown large enough. Returns the range of the full match if
+/// successful.
+pub fn find_into(
+    input: &[u8],
+    parsed: &Parsed,
+    start_index: usize,
+    budget: &mut Budget,
+    scratch: &mut Scratch,
+    captures: &mut Vec<CaptureRange>,
+) -> Result<Option<Range<usize>>> {
+    if !can_match(input, parsed, start_index) {
+        return Ok(None);
+    }
+
+    budget.check(|| Span::pattern(0..parsed.len))?;
+    let automata = automata(input, parsed, budget);
+    if let Some(Automata {
+        nfa,
+        dfa: Some(dfa),
+    }) = automata
+    {
+        match dfa.find(input, parsed, start_index, budget) {
+            Ok(Some(full_match)) => {
+                return captures_within(input, parsed, nfa, full_match, budget, scratch, captures);
+            }
+            Ok(None) => return Ok(None),
+            Err(Stop::Error(error)) => return Err(error),
+            Err(Stop::GaveUp) => {}
+        }
+    }
+
+    if let Some(Automata { nfa, .. }) = automata.filter(|automata| automata.nfa.backtracks) {
+        return nfa.find(
+            input,
+            parsed,
+            start_index,
+            &mut scratch.pike,
+            budget,
+            captures,
+        );
+    }
+
+    let Some((full_match, vm)) = search(input, parsed, start_index, budget, &mut scratch.vm)?
+    else {
+        return Ok(None);
+    };
+    write_captures(&vm, captures);
+    Ok(Some(full_match))
+}
+
+/// Checks whether the pattern matches anywhere in the input string, starting
+/// the search at `start_index` (0-based). If the DFA decides the match,
+/// nothing is allocated apart from the states which it caches. Otherwise, the
+/// other matchers allocate their working memory for the search.
+pub fn has_match(
+    input: &[u8],
+    parsed: &Parsed,
+    start_index: usize,
+    budget: &mut Budget,
+) -> Result<bool> {
+    if !can_match(input, parsed, start_index) {
+        return Ok(false);
+    }
+
+    budget.check(|| Span::pattern(0..parsed.len))?;
+    let automata = automata(input, parsed, budget);
+    if let Some(dfa) = automata.and_then(|automata| automata.dfa.as_ref()) {
+        match dfa.is_match(input, parsed, start_index, budget) {
+            Ok(matched) => return Ok(matched),
+            Err(Stop::Error(error)) => return Err(error),
+            Err(Stop::GaveUp) => {}
+        }
+    }
+
+    let mut scratch = Scratch::new();
+    if let Some(Automata { nfa, .. }) = automata.filter(|automata| automata.nfa.backtracks) {
+        return nfa.is_match(input, parsed, start_index, &mut scratch.pike, budget);
+    }
+
+    Ok(search(input, parsed, start_index, budget, &mut scratch.vm)?.is_some())
+}
+
+/// Returns the automata of the pattern if they should match it. A pattern
+/// which is only used once is left to the backtracking matcher, which
+/// remembers the pairs it has tried, unless the input is too long for that.
+fn automata<'a>(input: &[u8], parsed: &'a Parsed, budget: &Budget) -> Option<&'a Automata> {
+    if !regular(parsed, budget) || parsed.one_shot && vm::remembers(&parsed.program, input) {
+        return None;
+    }
+    parsed.automata()
+}
+
+/// Checks whether the automata can match the pattern within the limits.
+/// They do not open captures one at a time, so only the backtracking matcher
+/// can report a capture beyond the limit once it reaches it, like Lua.
+fn regular(parsed: &Parsed, budget: &Budget) -> bool {
+    parsed.is_regular() && parsed.captures <= budget.max_captures
+}
+
+/// Checks quickly whether the pattern could match the input after
+/// `start_index` at all. The rest of the input must be long enough for a
+/// match, contain the required literal of the pattern, and end with the
+/// literal suffix of the pattern if it is end anchored.
+fn can_match(input: &[u8], parsed: &Parsed, start_index: usize) -> bool {
+    let program = &parsed.program;
+    let Some(rest) = input.get(start_index..) else {
+        return false;
+    };
+    rest.len() >= program.min_len
+        && (!program.end_anchored || rest.ends_with(&program.suffix))
+        && program.required.as_ref().is_none_or(|required| {
+            rest.get(required.offset..)
+                .is_some_and(|rest| required.literal.find(rest).is_some())
+        })
+}
+
+/// The memory of the matchers, which can be reused by later matches so that
+/// they do not allocate.
+pub(crate) struct Scratch {
+    /// The memory of the backtracking matcher.
+    vm: VmScratch,
+    /// The memory of the automaton simulation.
+    pike: PikeScratch,
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self {
+            vm: VmScratch::new(),
+            pike: PikeScratch::new(),
+        }
+    }
+}
+
+/// Finds the captures of a match once its full range is known, by matching
+/// the pattern compiled into `nfa` only at the start of the range.
+fn captures_within(
+    input: &[u8],
+    parsed: &Parsed,
+    nfa: &Nfa,
+    full_match: Range<usize>,
+    budget: &mut Budget,
+    scratch: &mut Scratch,
+    captures: &mut Vec<CaptureRange>,
+) -> Result<Option<Range<usize>>> {
+    if nfa.captures == 0 {
+        captures.clear();
+        return Ok(Some(full_match));
+    }
+    if nfa.backtracks {
+        // The backtracking matcher could take exponential time even when it
+        // only tries a single start position
+        return nfa.find_at(
+            input,
+            parsed,
+            full_match.start,
+            &mut scratch.pike,
+            budget,
+            captures,
+        );
+    }
+
+    let mut vm = Vm::new(input, parsed, &mut scratch.vm, budget);
+    let Some(end) = vm.run(full_match.start)? else {
+        return Ok(None);
+    };
+    write_captures(&vm, captures);
+    Ok(Some(full_match.start..end))
+}
+
+/// Writes the ranges of the captures of a successful match from the final
+/// matcher state.
+fn write_captures(vm: &Vm<'_>, captures: &mut Vec<CaptureRange>) {
+    captures.clear();
+    captures.extend(vm.captures().iter().map(|capture| match capture {
+        CaptureState::Finished(range) => range.clone(),
+        CaptureState::Pending { .. } => unreachable!("checked by `search`"),
+    }));
+}
+
+/// Runs the matcher from each start position until the first match. Returns
+/// the range of the full match and the final matcher state if successful.
+fn search<'a>(
+    input: &'a [u8],
+    parsed: &'a Parsed,
+    start_index: usize,
+    budget: &'a mut Budget,
+    scratch: &'a mut VmScratch,
+) -> Result<Option<(Range<usize>, Vm<'a>)>> {
+    let mut vm = Vm::new(input, parsed, scratch, budget);
+
+    if parsed.literal {
+        // Each item of a literal pattern matches exactly one byte
+        return Ok(parsed
+            .prefilter
+            .find(input, start_index)
+            .map(|start| (start..start + parsed.items.len(), vm)));
+    }
+
+    let full_match = Cursor::new(input, parsed, start_index).search(input, parsed, &mut vm)?;
+    Ok(full_match.map(|full_match| (full_match, vm)))
+}
+
+/// Where a search by the backtracking matcher is, so that a search which ran
+/// out of steps can continue later.
+struct Cursor {
+    /// The start position of the current match attempt.
+    start: usize,
+    /// Where the required literal of the pattern occurs next, which a match
+    /// that starts any later cannot contain.
+    required_at: Option<usize>,
+}
+
+impl Cursor {
+    fn new(input: &[u8], parsed: &Parsed, start_index: usize) -> Self {
+        let mut start = start_index;
+        if parsed.program.end_anchored
+            && !parsed.anchored
+            && let Some(max_len) = parsed.program.max_len
+        {
+            // A match which starts any earlier cannot reach the end of the
+            // input
+            start = start.max(input.len().saturating_sub(max_len));
+        }
+        Self {
+            start,
+            required_at: None,
+        }
+    }
+
+    /// Runs the matcher from each start position until the first match,
+    /// after finishing the attempt which `vm` stopped in the middle of, if
+    /// there is one. Returns the range of the full match if successful.
+    fn search(
+        &mut self,
+        input: &[u8],
+        parsed: &Parsed,
+        vm: &mut Vm<'_>,
+    ) -> Result<Option<Range<usize>>> {
+        loop {
+            let end = if let Some(end) = vm.resume() {
+                end?
+            } else {
+                if !self.next_start(input, parsed) {
+                    return Ok(None);
+                }
+                vm.run(self.start)?
+            };
+
+            if let Some(end) = end {
+                // A capture group can only still be open if the pattern never
+                // closes it
+                if vm
+                    .captures()
+                    .iter()
+                    .any(|capture| matches!(capture, CaptureState::Pending { .. }))
+                {
+                    return Err(parsed.unfinished_error());
+                }
+
+                return Ok(Some(self.start..end));
+            }
+
+            if parsed.anchored {
+                return Ok(None);
+            }
+            self.start += 1;
+        }
+    }
+
+    /// Moves to the next position where a match can start, if there is one.
+    fn next_start(&mut self, input: &[u8], parsed: &Parsed) -> bool {
+        if self.start > input.len() {
+            return false;
+        }
+        if !parsed.anchored {
+            let Some(next) = parsed.prefilter.find(input, self.start) else {
+                return false;
+            };
+            self.start = next;
+        }
+        if input.len() - self.start < parsed.program.min_len {
+            return false;
+        }
+        if let Some(required) = &parsed.program.required
+            && self
+                .required_at
+                .is_none_or(|at| at < self.start + required.offset)
+        {
+            let from = self.start + required.offset;
+            let Some(at) = required.literal.find(&input[from..]) else {
+                return false;
+            };
+            self.required_at = Some(from + at);
+        }
+        true
+    }
+}
+
+/// A search by the backtracking matcher which ran out of fuel, and can
+/// continue where it stopped.
+pub(crate) struct Suspension {
+    /// Where the search is.
+    cursor: Cursor,
+    /// The state of the matcher, which is large.
+    scratch: Box<VmScratch>,
+    /// The number of steps which the whole search can still take, if it is
+    /// limited.
+    steps: Option<u64>,
+}
+
+/// The result of a search with a limited amount of fuel.
+pub(crate) enum Fueled {
+    /// The search finished, with the ranges of the match if successful.
+    Done(Option<MatchRanges>),
+    /// The search ran out of fuel.
+    Suspended(Suspension),
+}
+
+/// Like [`find_first_match`], but only runs the backtracking matcher, which
+/// stops once it has taken `fuel` steps, or one step if `fuel` is 0. The
+/// search can then be continued with [`resume`], with the same result as if
+/// it had never stopped. The step limit of `limits` still applies to the
+/// whole search.
+pub fn find_with_fuel(
+    input: &[u8],
+    parsed: &Parsed,
+    start_index: usize,
+    limits: &MatchLimits,
+    fuel: u64,
+) -> Result<Fueled> {
+    if !can_match(input, parsed, start_index) {
+        return Ok(Fueled::Done(None));
+    }
+    if parsed.literal {
+        // A literal pattern is found without running the matcher at all
+        return find_first_match(input, parsed, start_index, &mut Budget::new(limits))
+            .map(Fueled::Done);
+    }
+    let suspension = Suspension {
+        cursor: Cursor::new(input, parsed, start_index),
+        scratch: Box::new(VmScratch::new()),
+        steps: limits.max_steps(),
+    };
+    resume(input, parsed, limits, suspension, fuel)
+}
+
+/// Continues a search which ran out of fuel for up to `fuel` more steps. The
+/// input and the pattern must be the same as when the search started.
+pub fn resume(
+    input: &[u8],
+    parsed: &Parsed,
+    limits: &MatchLimits,
+    mut suspension: Suspension,
+    fuel: u64,
+) -> Result<Fueled> {
+    // Take at least one step, so that every call makes progress
+    let fuel = fuel.max(1);
+    let slice = suspension.steps.map_or(fuel, |steps| steps.min(fuel));
+    let mut budget = Budget::new(limits).with_steps(slice);
+    budget.check(|| Span::pattern(0..parsed.len))?;
+    let mut vm = Vm::restore(input, parsed, &mut suspension.scratch, &mut budget);
+    match suspension.cursor.search(input, parsed, &mut vm) {
+        Ok(Some(full_match)) => {
+            let mut captures = Vec::new();
+            write_captures(&vm, &mut captures);
+            Ok(Fueled::Done(Some(MatchRanges {
+                full_match,
+                captures,
+            })))
+        }
+        Ok(None) => Ok(Fueled::Done(None)),
+        Err(error @ Error::StepLimitExceeded { .. }) => {
+            if suspension.steps.is_some_and(|steps| steps <= fuel) {
+                // The whole search ran out of steps, not just this slice
+                return Err(error);
+            }
+            if let Some(steps) = &mut suspension.steps {
+                *steps -= slice;
+            }
+            Ok(Fueled::Suspended(suspension))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Intermediate state representation of a capture group.
+#[derive(Clone)]
+pub(crate) enum CaptureState {
+    /// The capture group is waiting to be closed.
+    Pending { start: usize },
+    /// The capture group is fully created.
+    Finished(CaptureRange),
+}
+
+impl CaptureState {
+    /// Finalise a ranged capture group.
+    fn finish(&mut self, end: usize) {
+        if let CaptureState::Pending { start } = self {
+            *self = CaptureState::Finished(CaptureRange::Range(*start..end));
+        }
+    }
+
+    /// Roll back a ranged capture group to a pending state.
+    fn revert(&mut self) {
+        if let CaptureState::Finished(CaptureRange::Range(range)) = self {
+            *self = CaptureState::Pending { start: range.start }
+        }
+    }
+}
+
+impl Default for CaptureState {
+    fn default() -> Self {
+        Self::Finished(<_>::default())
+    }
+}
@@ -0,0 +1,495 @@
+use super::{
+    Budget, ByteSet, CaptureRange,
+    compile::{Op, Program},
+    inline_vec::InlineVec,
+};
+use crate::{Result, Span, ast::Parsed};
+use std::ops::Range;
+
+/// A pattern compiled into a non-deterministic automaton, which the Pike VM
+/// simulates in O(n·m) time for an input of length n and m instructions.
+///
+/// Only patterns without back-references or balances can be compiled, since
+/// those are not regular.
+#[derive(Debug)]
+pub(crate) struct Nfa {
+    /// The instructions, ending with [`NfaInst::Match`].
+    pub(super) insts: Vec<NfaInst>,
+    /// The number of capture groups.
+    pub(super) captures: usize,
+    /// Bit mask of position capture group levels.
+    positions: u64,
+    /// Whether the pattern has a repetition or an optional item. Without one,
+    /// the backtracking matcher never backtracks and is just as fast.
+    pub(super) backtracks: bool,
+}
+
+/// An instruction of an [`Nfa`].
+#[derive(Debug)]
+pub(super) enum NfaInst {
+    /// Matches a single byte.
+    Byte(u8),
+    /// Matches a single byte in the set.
+    Set(ByteSet),
+    /// Continues at both instructions, preferring the first.
+    Split(usize, usize),
+    /// Continues at another instruction.
+    Jump(usize),
+    /// Records the current position in a slot. Slot 0 is the start of the
+    /// match, and the start and end of each capture group follow.
+    Save(usize),
+    /// Matches the transition from a byte not in the set to a byte in it.
+    Frontier(ByteSet),
+    /// Matches only at the end of the input.
+    AssertEnd,
+    /// Ends the pattern successfully.
+    Match,
+}
+
+impl Nfa {
+    /// Compiles a program into an automaton. Returns `None` if the program
+    /// cannot be simulated by an automaton.
+    pub fn new(program: &Program) -> Option<Self> {
+        let mut insts = Vec::new();
+        let mut captures = 0;
+        let mut positions = 0;
+        let mut backtracks = false;
+
+        for inst in &program.insts {
+            match &inst.op {
+                Op::Literal(literal) => insts.extend(literal.iter().copied().map(NfaInst::Byte)),
+                Op::Set(set) => insts.push(NfaInst::Set(*set)),
+                Op::Greedy { set, min } => {
+                    backtracks = true;
+                    if *min != 0 {
+                        insts.push(NfaInst::Set(*set));
+                    }
+                    push_repetition(&mut insts, *set, true);
+                }
+                Op::Lazy(set) => {
+                    backtracks = true;
+                    push_repetition(&mut insts, *set, false);
+                }
+                Op::Optional(set) => {
+                    backtracks = true;
+                    push_optional(&mut insts, *set);
+                }
+                Op::Open => {
+                    insts.push(NfaInst::Save(captures * 2 + 1));
+                    captures += 1;
+                }
+                Op::Position => {
+                    insts.push(NfaInst::Save(captures * 2 + 1));
+                    positions |= 1 << captures;
+                    captures += 1;
+                }
+                Op::Close { level } => insts.push(NfaInst::Save(level * 2 + 2)),
+                Op::Frontier(set) => insts.push(NfaInst::Frontier(*set)),
+                Op::AssertEnd => insts.push(NfaInst::AssertEnd),
+                Op::Match => insts.push(NfaInst::Match),
+                Op::Balance { .. } | Op::BackReference { .. } | Op::Error => return None,
+            }
+        }
+
+        Some(Self {
+            insts,
+            captures,
+            positions,
+            backtracks,
+        })
+    }
+
+    /// Compiles a program into an automaton for the reversed pattern, which
+    /// matches from the end of a match back to its start. Captures are left
+    /// out, and so is the end anchor, which the end of a match has already
+    /// been checked against. Frontiers are kept as they are, and must be
+    /// checked in the reverse direction. Returns `None` if the program cannot
+    /// be simulated by an automaton.
+    pub fn reverse(program: &Program) -> Option<Self> {
+        let mut insts = Vec::new();
+
+        for inst in program.insts.iter().rev() {
+            match &inst.op {
+                Op::Literal(literal) => {
+                    insts.extend(literal.iter().rev().copied().map(NfaInst::Byte));
+                }
+                Op::Set(set) => insts.push(NfaInst::Set(*set)),
+                Op::Greedy { set, min } => {
+                    if *min != 0 {
+                        insts.push(NfaInst::Set(*set));
+                    }
+                    push_repetition(&mut insts, *set, true);
+                }
+                // Which match is preferred does not matter for finding
+                // where a match starts
+                Op::Lazy(set) => push_repetition(&mut insts, *set, true),
+                Op::Optional(set) => push_optional(&mut insts, *set),
+                Op::Frontier(set) => insts.push(NfaInst::Frontier(*set)),
+                Op::Open | Op::Position | Op::Close { .. } | Op::AssertEnd | Op::Match => {}
+                Op::Balance { .. } | Op::BackReference { .. } | Op::Error => return None,
+            }
+        }
+        insts.push(NfaInst::Match);
+
+        Some(Self {
+            insts,
+            captures: 0,
+            positions: 0,
+            backtracks: false,
+        })
+    }
+
+    /// Finds the first match of the pattern in the input string, starting
+    /// the search at `start_index` (0-based), with the same result as the
+    /// backtracking matcher. Returns the range of the full match and writes
+    /// the ranges of the captures into `captures` if successful.
+    pub fn find(
+        &self,
+        input: &[u8],
+        parsed: &Parsed,
+        start_index: usize,
+        scratch: &mut PikeScratch,
+        budget: &mut Budget,
+        captures: &mut Vec<CaptureRange>,
+    ) -> Result<Option<Range<usize>>> {
+        self.find_from(
+            input,
+            parsed,
+            start_index,
+            parsed.anchored,
+            scratch,
+            budget,
+            captures,
+        )
+    }
+
+    /// Matches the pattern only at `start` (0-based), as if it was anchored.
+    pub fn find_at(
+        &self,
+        input: &[u8],
+        parsed: &Parsed,
+        start: usize,
+        scratch: &mut PikeScratch,
+        budget: &mut Budget,
+        captures: &mut Vec<CaptureRange>,
+    ) -> Result<Option<Range<usize>>> {
+        self.find_from(input, parsed, start, true, scratch, budget, captures)
+    }
+
+    // Clippy: These are the arguments of `find` and `find_at` together
+    #[allow(clippy::too_many_arguments)]
+    fn find_from(
+        &self,
+        input: &[u8],
+        parsed: &Parsed,
+        start_index: usize,
+        anchored: bool,
+        scratch: &mut PikeScratch,
+        budget: &mut Budget,
+        captures: &mut Vec<CaptureRange>,
+    ) -> Result<Option<Range<usize>>> {
+        let mut pike = Pike::new(self, input, self.captures * 2 + 1, scratch);
+        let Some(end) = pike.run(parsed, start_index, anchored, budget)? else {
+            return Ok(None);
+        };
+        let slots = &pike.scratch.matched;
+        captures.clear();
+        captures.extend((0..self.captures).map(|level| {
+            let start = slots[level * 2 + 1].unwrap_or_default();
+            if self.positions & (1 << level) == 0 {
+                CaptureRange::Range(start..slots[level * 2 + 2].unwrap_or(start))
+            } else {
+                CaptureRange::Position(start)
+            }
+        }));
+        Ok(Some(slots[0].unwrap_or_default()..end))
+    }
+
+    /// Checks whether the pattern matches anywhere in the input string,
+    /// starting the search at `start_index` (0-based), without tracking
+    /// captures. This does not allocate for small patterns.
+    pub fn is_match(
+        &self,
+        input: &[u8],
+        parsed: &Parsed,
+        start_index: usize,
+        scratch: &mut PikeScratch,
+        budget: &mut Budget,
+    ) -> Result<bool> {
+        Ok(Pike::new(self, input, 0, scratch)
+            .run(parsed, start_index, parsed.anchored, budget)?
+            .is_some())
+    }
+}
+
+/// The state of a Pike VM simulation of an [`Nfa`].
+struct Pike<'a> {
+    nfa: &'a Nfa,
+    input: &'a [u8],
+    /// The number of slots tracked for each thread, which is 0 if only
+    /// whether there is a match matters.
+    slot_count: usize,
+    /// The memory which is kept between simulations.
+    scratch: &'a mut PikeScratch,
+}
+
+/// The memory of a Pike VM simulation, which can be reused by later
+/// simulations so that they do not allocate.
+pub(crate) struct PikeScratch {
+    /// The threads at the current position, in priority order.
+    current: Threads,
+    /// The threads at the next position, in priority order.
+    next: Threads,
+    /// The pending work of `add_thread`.
+    stack: InlineVec<Work, INLINE_STATES>,
+    /// The capture slots of the thread being added.
+    slots: Vec<Option<usize>>,
+    /// The capture slots of the best match so far.
+    matched: Vec<Option<usize>>,
+}
+
+/// A set of threads, one per instruction.
+struct Threads {
+    /// The instructions of the threads, in priority order.
+    dense: InlineVec<usize, INLINE_STATES>,
+    /// The index of each instruction in `dense`, if it is there.
+    sparse: InlineVec<usize, INLINE_STATES>,
+    /// The capture slots of each thread, by instruction.
+    slots: Vec<Option<usize>>,
+}
+
+/// A pending step of adding a thread.
+#[derive(Clone, Copy)]
+enum Work {
+    /// Adds the thread at the given instruction.
+    At(usize),
+    /// Restores a slot after exploring the threads which set it.
+    Restore(usize, Option<usize>),
+}
+
+impl Default for Work {
+    fn default() -> Self {
+        Self::At(0)
+    }
+}
+
+impl<'a> Pike<'a> {
+    fn new(nfa: &'a Nfa, input: &'a [u8], slot_count: usize, scratch: &'a mut PikeScratch) -> Self {
+        scratch.current.reset(nfa.insts.len(), slot_count);
+        scratch.next.reset(nfa.insts.len(), slot_count);
+        scratch.stack.clear();
+        scratch.slots.clear();
+        scratch.slots.resize(slot_count, None);
+        Self {
+            nfa,
+            input,
+            slot_count,
+            scratch,
+        }
+    }
+
+    /// Runs the simulation. Returns the end of the leftmost match with the
+    /// highest priority if successful, and leaves its slots in
+    /// `scratch.matched`. If `anchored` is set, the match must start at
+    /// `start_index`. Each thread takes a step of `budget` at each position.
+    fn run(
+        &mut self,
+        parsed: &Parsed,
+        start_index: usize,
+        anchored: bool,
+        budget: &mut Budget,
+    ) -> Result<Option<usize>> {
+        let mut matched = None;
+        let mut pos = start_index;
+
+        while pos <= self.input.len() {
+            if matched.is_none() && (pos == start_index || !anchored) {
+                if self.scratch.current.dense.is_empty() && !anchored {
+                    // Skip straight to the next position where a match can
+                    // start
+                    let Some(next) = parsed.prefilter.find(self.input, pos) else {
+                        return Ok(None);
+                    };
+                    pos = next;
+                }
+                // A thread which starts here has a lower priority than the
+                // threads which started earlier
+                self.scratch.slots.fill(None);
+                if let Some(start) = self.scratch.slots.first_mut() {
+                    *start = Some(pos);
+                }
+                self.add_thread(false, 0, pos);
+            }
+
+            if self.scratch.current.dense.is_empty() {
+                break;
+            }
+
+            for i in 0..self.scratch.current.dense.len() {
+                budget.step(|| Span::pattern(0..parsed.len))?;
+                let pc = self.scratch.current.dense[i];
+                match &self.nfa.insts[pc] {
+                    NfaInst::Byte(c) if self.input.get(pos) == Some(c) => self.step(pc, pos),
+                    NfaInst::Set(set) if self.input.get(pos).is_some_and(|c| set.contains(*c)) => {
+                        self.step(pc, pos);
+                    }
+                    NfaInst::Match => {
+                        let slots = &self.scratch.current.slots
+                            [pc * self.slot_count..(pc + 1) * self.slot_count];
+                        self.scratch.matched.clear();
+                        self.scratch.matched.extend_from_slice(slots);
+                        matched = Some(pos);
+                        if self.slot_count == 0 {
+                            // Any match will do
+                            return Ok(matched);
+                        }
+                        // Threads with a lower priority cannot win anymore
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            core::mem::swap(&mut self.scratch.current, &mut self.scratch.next);
+            self.scratch.next.clear();
+            pos += 1;
+        }
+
+        Ok(matched)
+    }
+
+    /// Advances the thread at `pc` past the byte at `pos`.
+    fn step(&mut self, pc: usize, pos: usize) {
+        let slots = &self.scratch.current.slots[pc * self.slot_count..(pc + 1) * self.slot_count];
+        self.scratch.slots.copy_from_slice(slots);
+        self.add_thread(true, pc + 1, pos + 1);
+    }
+
+    /// Adds a thread at instruction `pc` and position `pos`, with the capture
+    /// slots in `self.scratch.slots`, to the current or the next set of threads.
+    /// Instructions which do not consume input are followed immediately, in
+    /// priority order.
+    fn add_thread(&mut self, next: bool, pc: usize, pos: usize) {
+        self.scratch.stack.push(Work::At(pc));
+        while let Some(work) = self.scratch.stack.pop() {
+            let pc = match work {
+                Work::At(pc) => pc,
+                Work::Restore(slot, value) => {
+                    self.scratch.slots[slot] = value;
+                    continue;
+                }
+            };
+
+            let threads = if next {
+                &mut self.scratch.next
+            } else {
+                &mut self.scratch.current
+            };
+            if threads.contains(pc) {
+                continue;
+            }
+            threads.insert(pc, &self.scratch.slots);
+
+            match &self.nfa.insts[pc] {
+                NfaInst::Split(first, second) => {
+                    self.scratch.stack.push(Work::At(*second));
+                    self.scratch.stack.push(Work::At(*first));
+                }
+                NfaInst::Jump(to) => self.scratch.stack.push(Work::At(*to)),
+                NfaInst::Save(slot) => {
+                    if let Some(value) = self.scratch.slots.get_mut(*slot) {
+                        self.scratch.stack.push(Work::Restore(*slot, *value));
+                        *value = Some(pos);
+                    }
+                    self.scratch.stack.push(Work::At(pc + 1));
+                }
+                NfaInst::Frontier(set) => {
+                    // Lua manual: “The beginning and end of the subject are
+                    // handled as if they were the character '\0'.”
+                    let first = if pos == 0 { b'\0' } else { self.input[pos - 1] };
+                    let last = self.input.get(pos).copied().unwrap_or(b'\0');
+                    if !set.contains(first) && set.contains(last) {
+                        self.scratch.stack.push(Work::At(pc + 1));
+                    }
+                }
+                NfaInst::AssertEnd => {
+                    if pos == self.input.len() {
+                        self.scratch.stack.push(Work::At(pc + 1));
+                    }
+                }
+                NfaInst::Byte(_) | NfaInst::Set(_) | NfaInst::Match => {}
+            }
+        }
+    }
+}
+
+impl PikeScratch {
+    pub fn new() -> Self {
+        Self {
+            current: Threads::new(),
+            next: Threads::new(),
+            stack: InlineVec::new(),
+            slots: Vec::new(),
+            matched: Vec::new(),
+        }
+    }
+}
+
+impl Threads {
+    fn new() -> Self {
+        Self {
+            dense: InlineVec::new(),
+            sparse: InlineVec::new(),
+            slots: Vec::new(),
+        }
+    }
+
+    /// Empties the set for a simulation with `len` instructions and
+    /// `slot_count` slots per thread.
+    fn reset(&mut self, len: usize, slot_count: usize) {
+        self.dense.clear();
+        self.sparse.fill(len, 0);
+        self.slots.clear();
+        self.slots.resize(len * slot_count, None);
+    }
+
+    fn contains(&self, pc: usize) -> bool {
+        self.dense.get(self.sparse[pc]) == Some(&pc)
+    }
+
+    fn insert(&mut self, pc: usize, slots: &[Option<usize>]) {
+        self.sparse[pc] = self.dense.len();
+        self.dense.push(pc);
+        if !slots.is_empty() {
+            self.slots[pc * slots.len()..(pc + 1) * slots.len()].copy_from_slice(slots);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+    }
+}
+
+/// Adds instructions which match any number of bytes in the set, preferring
+/// more if `greedy` is set, and fewer otherwise.
+fn push_repetition(insts: &mut Vec<NfaInst>, set: ByteSet, greedy: bool) {
+    let split = insts.len();
+    insts.extend([
+        if greedy {
+            NfaInst::Split(split + 1, split + 3)
+        } else {
+            NfaInst::Split(split + 3, split + 1)
+        },
+        NfaInst::Set(set),
+        NfaInst::Jump(split),
+    ]);
+}
+
+/// Adds instructions which match a byte in the set if possible, or nothing.
+fn push_optional(insts: &mut Vec<NfaInst>, set: ByteSet) {
+    let split = insts.len();
+    insts.extend([NfaInst::Split(split + 1, split + 2), NfaInst::Set(set)]);
+}
+
+/// The number of instructions for which the simulation does not allocate.
+const INLINE_STATES: usize = 64;
@@ -0,0 +1,76 @@
+use super::{ByteSet, TwoWay};
+use crate::ast::{Item, ItemKind, Quantifier, Single};
+
+/// A fast scan for the positions where a match of an unanchored pattern can
+/// start, so that the matcher does not have to try every position.
+#[derive(Debug)]
+pub(crate) enum Prefilter {
+    /// Every position is a candidate.
+    None,
+    /// A match must start with this string.
+    Literal(TwoWay),
+    /// A match must start with one of the bytes in this set.
+    Bytes(ByteSet),
+}
+
+impl Prefilter {
+    /// Analyses the leading items of a pattern for a required literal prefix
+    /// or a required first byte.
+    pub fn new(anchored: bool, items: &[Item]) -> Self {
+        if anchored {
+            return Self::None;
+        }
+
+        let mut prefix = Vec::new();
+        for item in items {
+            match &item.kind {
+                // Captures do not consume any input
+                ItemKind::OpenCapture
+                | ItemKind::PositionCapture { .. }
+                | ItemKind::CloseCapture { .. } => {}
+                ItemKind::Single {
+                    class: Single::Literal(c),
+                    quantifier,
+                } if matches!(quantifier, None | Some(Quantifier::OneOrMore)) => {
+                    prefix.push(*c);
+                    if quantifier.is_some() {
+                        break;
+                    }
+                }
+                ItemKind::Single { class, quantifier }
+                    if prefix.is_empty()
+                        && matches!(quantifier, None | Some(Quantifier::OneOrMore)) =>
+                {
+                    let bytes = class.bytes();
+                    if bytes.is_full() {
+                        return Self::None;
+                    }
+                    return Self::Bytes(bytes);
+                }
+                ItemKind::Balance { open, .. } => {
+                    prefix.push(*open);
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        if prefix.is_empty() {
+            Self::None
+        } else {
+            Self::Literal(TwoWay::new(prefix.into()))
+        }
+    }
+
+    /// Returns the first candidate start position at or after `start`, or
+    /// `None` if a match cannot start anywhere after it.
+    pub fn find(&self, input: &[u8], start: usize) -> Option<usize> {
+        let rest = input.get(start..)?;
+        let found = match self {
+            Self::None => return Some(start),
+            Self::Literal(prefix) => prefix.find(rest),
+            Self::Bytes(bytes) => rest.iter().position(|&c| bytes.contains(c)),
+        };
+        found.map(|pos| start + pos)
+    }
+}
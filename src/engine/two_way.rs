@@ -0,0 +1,140 @@
+/// A substring searcher using the Crochemore–Perrin Two-Way algorithm, which
+/// runs in linear time and constant space. The needle is either owned, or
+/// borrowed for a single search so that nothing is allocated.
+#[derive(Debug)]
+pub(crate) struct TwoWay<N = Box<[u8]>> {
+    /// The string to search for.
+    needle: N,
+    /// The critical position, which splits the needle into a left and a right
+    /// part.
+    crit_pos: usize,
+    /// The period of the needle, or a shift which is safe to use if the
+    /// needle is not periodic.
+    period: usize,
+    /// Whether the needle has no short period, in which case the matched
+    /// prefix does not need to be remembered after a shift.
+    long_period: bool,
+}
+
+impl<N: AsRef<[u8]>> TwoWay<N> {
+    /// Creates a searcher for the given string.
+    pub fn new(needle: N) -> Self {
+        let bytes = needle.as_ref();
+        if bytes.is_empty() {
+            return Self {
+                needle,
+                crit_pos: 0,
+                period: 1,
+                long_period: true,
+            };
+        }
+
+        let (crit_pos_false, period_false) = maximal_suffix(bytes, false);
+        let (crit_pos_true, period_true) = maximal_suffix(bytes, true);
+        let (crit_pos, period) = if crit_pos_false > crit_pos_true {
+            (crit_pos_false, period_false)
+        } else {
+            (crit_pos_true, period_true)
+        };
+
+        if bytes[..crit_pos] == bytes[period..period + crit_pos] {
+            Self {
+                needle,
+                crit_pos,
+                period,
+                long_period: false,
+            }
+        } else {
+            let period = crit_pos.max(bytes.len() - crit_pos) + 1;
+            Self {
+                needle,
+                crit_pos,
+                period,
+                long_period: true,
+            }
+        }
+    }
+
+    /// Returns the position of the first occurrence of the needle in the
+    /// haystack.
+    pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+        let needle = self.needle.as_ref();
+        match needle {
+            [] => return Some(0),
+            [c] => return haystack.iter().position(|b| b == c),
+            _ => {}
+        }
+
+        let mut pos = 0;
+        // The length of the prefix of the needle known to match at `pos`
+        let mut memory = 0;
+        'search: while pos + needle.len() <= haystack.len() {
+            let window = &haystack[pos..pos + needle.len()];
+
+            // Match the right part, then the left part
+            let start = if self.long_period {
+                self.crit_pos
+            } else {
+                self.crit_pos.max(memory)
+            };
+            for i in start..needle.len() {
+                if needle[i] != window[i] {
+                    pos += i - self.crit_pos + 1;
+                    memory = 0;
+                    continue 'search;
+                }
+            }
+
+            let start = if self.long_period { 0 } else { memory };
+            for i in (start..self.crit_pos).rev() {
+                if needle[i] != window[i] {
+                    pos += self.period;
+                    if !self.long_period {
+                        memory = needle.len() - self.period;
+                    }
+                    continue 'search;
+                }
+            }
+
+            return Some(pos);
+        }
+
+        None
+    }
+}
+
+/// Computes the maximal suffix of the needle in lexicographic order, or in
+/// reverse order if `reversed` is set. Returns the start of the suffix and its
+/// period.
+fn maximal_suffix(needle: &[u8], reversed: bool) -> (usize, usize) {
+    let mut left = 0;
+    let mut right = 1;
+    let mut offset = 0;
+    let mut period = 1;
+
+    while let Some(&a) = needle.get(right + offset) {
+        let b = needle[left + offset];
+        if (a < b && !reversed) || (a > b && reversed) {
+            // The suffix is smaller, so the period is the whole prefix so far
+            right += offset + 1;
+            offset = 0;
+            period = right - left;
+        } else if a == b {
+            // Advance through the repetition of the current period
+            if offset + 1 == period {
+                right += offset + 1;
+                offset = 0;
+            } else {
+                offset += 1;
+            }
+        } else {
+            // The suffix is larger, so start over from the current position
+            left = right;
+            right += 1;
+            offset = 0;
+            period = 1;
+        }
+    }
+
+    (left, period)
+}
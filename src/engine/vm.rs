@@ -0,0 +1,482 @@
+use super::{
+    Budget, ByteSet, CaptureRange, CaptureState,
+    compile::{Op, Program},
+    inline_vec::InlineVec,
+    regular,
+};
+use crate::{Error, LUA_MAXCAPTURES, Result, ast::Parsed};
+
+/// The matching VM, which runs a compiled [`Program`] against an input
+/// string. Instead of recursing, it keeps the points it can backtrack to on
+/// an explicit stack on the heap, so that deep backtracking cannot overflow
+/// the native stack of the thread.
+///
+/// Once it has backtracked a lot, the VM also remembers which pairs of
+/// instruction and input position it has tried, like a bit-state backtracker.
+/// Every instruction only moves forward, so a pair which is tried again must
+/// have failed before, and unless a back-reference depends on the captures,
+/// it fails again. Repetitions also stop at the pairs they were tried at
+/// before, so every pair is tried at most once. This bounds the work of a
+/// search to O(n·m) for an input of length n and m instructions, except that
+/// a balance item scans up to n bytes each time it is tried.
+///
+/// The pairs are not remembered for patterns with back-references, or once
+/// there are more than [`MAX_MEMO_BITS`] of them, which is a few megabytes of
+/// input for a small pattern. The work of those searches is not bounded.
+pub(super) struct Vm<'a> {
+    /// The input string to match.
+    input: &'a [u8],
+    /// The parsed pattern to match.
+    parsed: &'a Parsed,
+    /// The memory which is kept between matches.
+    scratch: &'a mut VmScratch,
+    /// The limits of the search.
+    budget: &'a mut Budget,
+    /// The maximum recursion depth of the equivalent recursive matcher.
+    max_depth: usize,
+}
+
+/// The memory of the matching VM, which can be reused by later matches so
+/// that they do not allocate.
+///
+/// This holds all of the state of a search, so that a search which ran out
+/// of steps can be resumed later with [`Vm::restore`].
+pub(crate) struct VmScratch {
+    /// Number of capture groups.
+    level: usize,
+    /// Intermediate capture group states.
+    captures: [CaptureState; LUA_MAXCAPTURES],
+    /// The number of times the VM has backtracked, until it starts to
+    /// remember the pairs it tries.
+    backtracks: usize,
+    /// The instruction and input position at which the VM ran out of steps
+    /// in the middle of a match attempt.
+    stopped: Option<(usize, usize)>,
+    /// The points to backtrack to, innermost last. The first frames are kept
+    /// inline so that most matches do not allocate.
+    stack: InlineVec<Frame, INLINE_FRAMES>,
+    /// The pairs of instruction and input position which have been tried, as
+    /// a bit set, or empty if they are not remembered (yet).
+    tried: Vec<u64>,
+}
+
+/// A point on the backtracking stack.
+#[derive(Clone, Copy, Debug, Default)]
+enum Frame {
+    /// A greedy repetition which matched `count` bytes from `s`, and can give
+    /// some of them back to retry instruction `pc`.
+    Greedy {
+        pc: usize,
+        s: usize,
+        count: usize,
+        min: usize,
+    },
+    /// A lazy repetition which can take the byte at `s` to retry instruction
+    /// `pc`.
+    Lazy { pc: usize, s: usize },
+    /// An optional item which matched, and can instead match nothing at `s`
+    /// to retry instruction `pc`.
+    Optional { pc: usize, s: usize },
+    /// A capture group was started, and must be dropped when backtracking.
+    #[default]
+    Open,
+    /// The capture group at the given level was finished, and must be
+    /// reopened when backtracking.
+    Close { level: usize },
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(
+        input: &'a [u8],
+        parsed: &'a Parsed,
+        scratch: &'a mut VmScratch,
+        budget: &'a mut Budget,
+    ) -> Self {
+        scratch.tried.clear();
+        scratch.level = 0;
+        scratch.backtracks = 0;
+        scratch.stopped = None;
+        Self::restore(input, parsed, scratch, budget)
+    }
+
+    /// Creates a VM which continues the search whose state is in `scratch`,
+    /// for the same input and pattern.
+    pub fn restore(
+        input: &'a [u8],
+        parsed: &'a Parsed,
+        scratch: &'a mut VmScratch,
+        budget: &'a mut Budget,
+    ) -> Self {
+        // The automata have no depth limit, so neither does the VM for the
+        // patterns which they can match, and both give the same results.
+        // Every instruction moves forward, so the stack never holds more
+        // frames than the program has instructions
+        let max_depth = if regular(parsed, budget) {
+            usize::MAX
+        } else {
+            budget.max_depth
+        };
+        Self {
+            input,
+            parsed,
+            scratch,
+            budget,
+            max_depth,
+        }
+    }
+
+    /// Resets the VM for a new match attempt.
+    fn reset(&mut self) {
+        self.scratch.level = 0;
+        self.scratch.stack.clear();
+    }
+
+    /// Returns the captures of the current match attempt.
+    pub fn captures(&self) -> &[CaptureState] {
+        &self.scratch.captures[..self.scratch.level]
+    }
+
+    /// Runs the program with the input starting at position `s`. Returns the
+    /// end position of the match if successful.
+    ///
+    /// If the VM runs out of steps, it fails with
+    /// [`Error::StepLimitExceeded`] and remembers where it stopped, so that
+    /// [`Vm::resume`] can continue the attempt.
+    pub fn run(&mut self, s: usize) -> Result<Option<usize>> {
+        self.reset();
+        self.run_from(0, s)
+    }
+
+    /// Continues the match attempt which ran out of steps. Returns `None` if
+    /// there is none.
+    pub fn resume(&mut self) -> Option<Result<Option<usize>>> {
+        let (pc, s) = self.scratch.stopped.take()?;
+        Some(self.run_from(pc, s))
+    }
+
+    /// Runs the program from instruction `pc` at input position `s`.
+    fn run_from(&mut self, mut pc: usize, mut s: usize) -> Result<Option<usize>> {
+        let parsed = self.parsed;
+        let program = self.program();
+        loop {
+            if let Err(error) = self.budget.step(|| parsed.span(program.insts[pc].item)) {
+                self.scratch.stopped = Some((pc, s));
+                return Err(error);
+            }
+            let matched = self.first_try(pc, s)
+                && match &program.insts[pc].op {
+                    Op::Literal(literal) => {
+                        if self.input[s..].starts_with(literal) {
+                            s += literal.len();
+                            pc += 1;
+                            continue;
+                        }
+                        false
+                    }
+                    Op::Set(set) => {
+                        if self.is_single_match(s, set) {
+                            s += 1;
+                            pc += 1;
+                            continue;
+                        }
+                        false
+                    }
+                    Op::Greedy { set, min } => {
+                        let count = self.expand(pc, s, set, *min);
+                        if count >= *min {
+                            pc += 1;
+                            // If nothing matched, there is nothing to give back
+                            if count != 0 {
+                                let frame = Frame::Greedy {
+                                    pc,
+                                    s,
+                                    count,
+                                    min: *min,
+                                };
+                                self.push(frame, pc)?;
+                                s += count;
+                            }
+                            continue;
+                        }
+                        false
+                    }
+                    Op::Lazy(set) => {
+                        pc += 1;
+                        if self.is_single_match(s, set) {
+                            self.push(Frame::Lazy { pc, s }, pc)?;
+                        }
+                        continue;
+                    }
+                    Op::Optional(set) => {
+                        pc += 1;
+                        if self.is_single_match(s, set) {
+                            self.push(Frame::Optional { pc, s }, pc)?;
+                            s += 1;
+                        }
+                        continue;
+                    }
+                    Op::Open => {
+                        self.check_captures(pc)?;
+                        self.scratch.captures[self.scratch.level] =
+                            CaptureState::Pending { start: s };
+                        self.scratch.level += 1;
+                        pc += 1;
+                        self.push(Frame::Open, pc)?;
+                        continue;
+                    }
+                    Op::Position => {
+                        self.check_captures(pc)?;
+                        self.scratch.captures[self.scratch.level] =
+                            CaptureState::Finished(CaptureRange::Position(s));
+                        self.scratch.level += 1;
+                        pc += 1;
+                        self.push(Frame::Open, pc)?;
+                        continue;
+                    }
+                    Op::Close { level } => {
+                        self.scratch.captures[*level].finish(s);
+                        pc += 1;
+                        self.push(Frame::Close { level: *level }, pc)?;
+                        continue;
+                    }
+                    Op::Balance { open, close } => {
+                        if let Some(next) = self.match_balance(s, *open, *close) {
+                            s = next;
+                            pc += 1;
+                            continue;
+                        }
+                        false
+                    }
+                    Op::Frontier(set) => {
+                        // Lua manual: “The beginning and end of the subject are
+                        // handled as if they were the character '\0'.”
+                        let first = if s == 0 { b'\0' } else { self.input[s - 1] };
+                        let last = self.input.get(s).copied().unwrap_or(b'\0');
+                        if !set.contains(first) && set.contains(last) {
+                            pc += 1;
+                            continue;
+                        }
+                        false
+                    }
+                    Op::BackReference { level } => {
+                        if let Some(next) = self.match_capture(s, *level) {
+                            s = next;
+                            pc += 1;
+                            continue;
+                        }
+                        false
+                    }
+                    Op::AssertEnd => {
+                        if s == self.input.len() {
+                            pc += 1;
+                            continue;
+                        }
+                        false
+                    }
+                    Op::Match => true,
+                    Op::Error => {
+                        if let Some((_, error)) = &self.parsed.error {
+                            return Err(error.clone());
+                        }
+                        unreachable!("only compiled for patterns with an error");
+                    }
+                };
+
+            if matched {
+                return Ok(Some(s));
+            }
+
+            // Backtrack to the innermost point which can still be retried
+            let Some((next_pc, next_s)) = self.backtrack() else {
+                return Ok(None);
+            };
+            pc = next_pc;
+            s = next_s;
+            self.count_backtrack();
+        }
+    }
+
+    /// Counts the bytes in the set from position `s` which the greedy
+    /// repetition at instruction `pc` takes.
+    ///
+    /// A repetition tried from position `t` goes on to try the rest of the
+    /// pattern at every position from `t + min` to the end of its run, so
+    /// once the tried pairs are remembered, the repetition stops before a
+    /// position where the rest of the pattern is known to fail. This way,
+    /// each run of bytes is only scanned once.
+    fn expand(&mut self, pc: usize, s: usize, set: &ByteSet, min: usize) -> usize {
+        let mut count = 0;
+        while self.is_single_match(s + count, set) {
+            let from = s + count + 1 - min;
+            if from > s && !self.first_try(pc, from) {
+                break;
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// Records that the instruction `pc` is tried at input position `s`.
+    /// Returns `false` if the pair has been tried before, and so fails.
+    fn first_try(&mut self, pc: usize, s: usize) -> bool {
+        if self.scratch.tried.is_empty() {
+            return true;
+        }
+        let bit = pc * (self.input.len() + 1) + s;
+        let (word, mask) = (bit / 64, 1 << (bit % 64));
+        let first = self.scratch.tried[word] & mask == 0;
+        self.scratch.tried[word] |= mask;
+        first
+    }
+
+    /// Counts a backtrack, and starts remembering the tried pairs once there
+    /// have been enough that it is worth the memory.
+    fn count_backtrack(&mut self) {
+        if !self.scratch.tried.is_empty() {
+            return;
+        }
+        self.scratch.backtracks += 1;
+        if self.scratch.backtracks == MEMO_AFTER && remembers(self.program(), self.input) {
+            let bits = self.program().insts.len() * (self.input.len() + 1);
+            self.scratch.tried.resize(bits.div_ceil(64), 0);
+        }
+    }
+
+    /// Returns the compiled pattern.
+    fn program(&self) -> &'a Program {
+        &self.parsed.program
+    }
+
+    /// Adds a backtracking point, or fails if the pattern is too complex.
+    /// `pc` is the instruction which runs next, for the error span.
+    fn push(&mut self, frame: Frame, pc: usize) -> Result<()> {
+        // The recursion depth of the equivalent recursive matcher, which
+        // counts the initial call too
+        if self.scratch.stack.len() + 1 >= self.max_depth {
+            return Err(Error::TooComplex {
+                span: self.parsed.span(self.program().insts[pc].item),
+            });
+        }
+        self.scratch.stack.push(frame);
+        Ok(())
+    }
+
+    /// Fails if the capture which instruction `pc` opens is one more than
+    /// the limit allows.
+    fn check_captures(&self, pc: usize) -> Result<()> {
+        if self.scratch.level >= self.budget.max_captures {
+            return Err(Error::TooManyCaptures {
+                span: self.parsed.span(self.program().insts[pc].item),
+            });
+        }
+        Ok(())
+    }
+
+    /// Unwinds the stack to the innermost backtracking point which has
+    /// another alternative, and returns the instruction and input position to
+    /// resume from. Returns `None` if there are no alternatives left.
+    fn backtrack(&mut self) -> Option<(usize, usize)> {
+        while let Some(frame) = self.scratch.stack.last_mut() {
+            match frame {
+                Frame::Greedy { pc, s, count, min } => {
+                    if *count > *min {
+                        *count -= 1;
+                        return Some((*pc, *s + *count));
+                    }
+                }
+                Frame::Lazy { pc, s } => {
+                    let Op::Lazy(set) = &self.parsed.program.insts[*pc - 1].op else {
+                        unreachable!("lazy frames come from lazy instructions");
+                    };
+                    if self.input.get(*s).is_some_and(|c| set.contains(*c)) {
+                        *s += 1;
+                        let resume = (*pc, *s);
+                        // Like a greedy repetition, stop at a position which
+                        // the repetition was tried from before
+                        if self.first_try(resume.0 - 1, resume.1) {
+                            return Some(resume);
+                        }
+                    }
+                }
+                Frame::Optional { pc, s } => {
+                    let resume = (*pc, *s);
+                    self.scratch.stack.pop();
+                    return Some(resume);
+                }
+                Frame::Open => self.scratch.level -= 1,
+                Frame::Close { level } => self.scratch.captures[*level].revert(),
+            }
+            self.scratch.stack.pop();
+        }
+        None
+    }
+
+    /// Matches a pattern balance item. If successful, returns the next position
+    /// of the input.
+    fn match_balance(&self, s: usize, open: u8, close: u8) -> Option<usize> {
+        // It is possible that we are at the end of the input.
+        if self.input.get(s).copied().unwrap_or(b'\0') != open {
+            return None;
+        }
+
+        let mut count = 1;
+
+        for s in s + 1..self.input.len() {
+            if self.input[s] == close {
+                count -= 1;
+                if count == 0 {
+                    return Some(s + 1);
+                }
+            } else if self.input[s] == open {
+                count += 1;
+            }
+        }
+
+        None
+    }
+
+    /// Matches the capture group at the given level to the input string.
+    /// Returns the next position of the input string if successful.
+    fn match_capture(&self, s: usize, level: usize) -> Option<usize> {
+        let CaptureState::Finished(CaptureRange::Range(range)) = &self.scratch.captures[level]
+        else {
+            unreachable!("back-references are checked during compilation");
+        };
+        let end = s + range.len();
+        (self.input.get(range.clone()) == self.input.get(s..end)).then_some(end)
+    }
+
+    /// Checks whether the input matches the character class.
+    fn is_single_match(&self, s: usize, set: &ByteSet) -> bool {
+        self.input.get(s).is_some_and(|c| set.contains(*c))
+    }
+}
+
+impl VmScratch {
+    pub fn new() -> Self {
+        Self {
+            level: 0,
+            captures: <_>::default(),
+            backtracks: 0,
+            stopped: None,
+            stack: InlineVec::new(),
+            tried: Vec::new(),
+        }
+    }
+}
+
+/// Checks whether the VM remembers the pairs it has tried when it matches the
+/// program against the input.
+pub(super) fn remembers(program: &Program, input: &[u8]) -> bool {
+    !program.back_references && program.insts.len() * (input.len() + 1) <= MAX_MEMO_BITS
+}
+
+/// The number of backtracking frames kept inline.
+const INLINE_FRAMES: usize = 32;
+
+/// The number of backtracks after which the VM starts to remember the pairs
+/// of instruction and input position it has tried.
+const MEMO_AFTER: usize = 256;
+
+/// The maximum size of the set of tried pairs, in bits.
+const MAX_MEMO_BITS: usize = 1 << 24;
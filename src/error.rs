@@ -0,0 +1,212 @@
+use core::fmt;
+use std::ops::Range;
+
+/// An error in a pattern or replacement string, or a limit which stopped
+/// matching.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+pub enum Error {
+    /// Matching backtracked deeper than the depth limit.
+    #[error("pattern too complex at {span}")]
+    TooComplex { span: Span },
+    /// Matching took more steps than the step limit.
+    #[error("step limit exceeded at {span}")]
+    StepLimitExceeded { span: Span },
+    /// Matching was cancelled by the cancellation flag.
+    #[error("matching cancelled at {span}")]
+    Cancelled { span: Span },
+    /// Matching ran past the deadline.
+    #[error("matching deadline exceeded at {span}")]
+    DeadlineExceeded { span: Span },
+    /// A match opened more captures than the capture limit.
+    #[error("too many captures at {span}")]
+    TooManyCaptures { span: Span },
+    /// A capture was closed without being open.
+    #[error("invalid pattern capture at {span}")]
+    InvalidPatternCapture { span: Span },
+    /// A frontier `%f` was not followed by a set.
+    #[error("missing '[' after '%f' in pattern at {span}")]
+    IncompleteFrontier { span: Span },
+    /// A balance `%b` was not followed by two characters.
+    #[error("malformed pattern (missing arguments to '%b') at {span}")]
+    MissingBalanceArgs { span: Span },
+    /// A back-reference or replacement referred to a capture which does not
+    /// exist or is not finished.
+    #[error("invalid capture index %{index} at {span}")]
+    InvalidCaptureIndex { span: Span, index: usize },
+    /// The pattern ended with an escaping `%`.
+    #[error("malformed pattern (ends with '%') at {span}")]
+    EndsWithPercent { span: Span },
+    /// A set was never closed with `]`.
+    #[error("malformed pattern (missing ']') at {span}")]
+    EndsWithoutBracket { span: Span },
+    /// A capture was never closed.
+    #[error("unfinished capture at {span}")]
+    UnfinishedCapture { span: Span },
+    /// A `%` in the replacement string was not followed by a digit or `%`.
+    #[error("invalid use of '%' in replacement string at {span}")]
+    InvalidReplacement { span: Span },
+}
+
+impl Error {
+    /// Returns the part of the pattern or replacement string which caused the
+    /// error.
+    #[must_use]
+    pub fn span(&self) -> &Span {
+        match self {
+            Self::TooComplex { span }
+            | Self::StepLimitExceeded { span }
+            | Self::Cancelled { span }
+            | Self::DeadlineExceeded { span }
+            | Self::TooManyCaptures { span }
+            | Self::InvalidPatternCapture { span }
+            | Self::IncompleteFrontier { span }
+            | Self::MissingBalanceArgs { span }
+            | Self::InvalidCaptureIndex { span, .. }
+            | Self::EndsWithPercent { span }
+            | Self::EndsWithoutBracket { span }
+            | Self::UnfinishedCapture { span }
+            | Self::InvalidReplacement { span } => span,
+        }
+    }
+
+    /// Renders the error for display to a user, with the offending part of
+    /// the pattern or replacement string underlined:
+    ///
+    /// ```text
+    /// malformed pattern (ends with '%') at pattern byte 6
+    ///   pattern: (%w+)=%
+    ///                  ^ nothing follows '%'
+    /// ```
+    ///
+    /// `pattern` and `replacement` are the strings that were given to the
+    /// function which returned the error. The replacement string is only used
+    /// when the error is in the replacement string, so it can be empty
+    /// otherwise.
+    #[must_use]
+    pub fn render(&self, pattern: &[u8], replacement: &[u8]) -> String {
+        let span = self.span();
+        let (name, source) = match span.source {
+            Source::Pattern => ("pattern", pattern),
+            Source::Replacement => ("replacement", replacement),
+        };
+        let (text, start, end) = underline(source, &span.range);
+        format!(
+            "{self}\n  {name}: {text}\n  {:indent$}{} {}",
+            "",
+            "^".repeat((end - start).max(1)),
+            self.label(),
+            indent = name.len() + 2 + start
+        )
+    }
+
+    /// A short description of the problem with the underlined part of the
+    /// string.
+    fn label(&self) -> String {
+        match self {
+            Self::TooComplex { .. } => "matching gave up here".into(),
+            Self::StepLimitExceeded { .. } => "matching ran out of steps here".into(),
+            Self::Cancelled { .. } => "matching was cancelled here".into(),
+            Self::DeadlineExceeded { .. } => "matching ran out of time here".into(),
+            Self::TooManyCaptures { .. } => "exceeds the capture limit".into(),
+            Self::InvalidPatternCapture { .. } => "no open capture to close".into(),
+            Self::IncompleteFrontier { .. } => "expected '[' after '%f'".into(),
+            Self::MissingBalanceArgs { .. } => "expected two characters after '%b'".into(),
+            Self::InvalidCaptureIndex { span, index } => match span.source {
+                Source::Pattern => format!("no finished capture {index}"),
+                Source::Replacement => format!("no capture {index}"),
+            },
+            Self::EndsWithPercent { .. } => "nothing follows '%'".into(),
+            Self::EndsWithoutBracket { .. } => "set is never closed".into(),
+            Self::UnfinishedCapture { .. } => "capture is never closed".into(),
+            Self::InvalidReplacement { .. } => "expected a digit or '%' after '%'".into(),
+        }
+    }
+}
+
+/// The string that a [`Span`] points into.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// The pattern string.
+    Pattern,
+    /// The replacement string given to [`gsub`](crate::gsub).
+    Replacement,
+}
+
+/// A range of bytes in a pattern or replacement string.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Span {
+    /// The string that the range points into.
+    pub source: Source,
+    /// The 0-based byte range. This is empty if the error is at a position
+    /// between two bytes, like the end of the string.
+    pub range: Range<usize>,
+}
+
+impl Span {
+    /// Creates a span of bytes in a pattern string.
+    #[must_use]
+    pub fn pattern(range: Range<usize>) -> Self {
+        Self {
+            source: Source::Pattern,
+            range,
+        }
+    }
+
+    /// Creates a span of bytes in a replacement string.
+    #[must_use]
+    pub fn replacement(range: Range<usize>) -> Self {
+        Self {
+            source: Source::Replacement,
+            range,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let source = match self.source {
+            Source::Pattern => "pattern",
+            Source::Replacement => "replacement",
+        };
+        if self.range.len() > 1 {
+            write!(f, "{source} bytes {}..{}", self.range.start, self.range.end)
+        } else {
+            write!(f, "{source} byte {}", self.range.start)
+        }
+    }
+}
+
+/// Converts a string to printable text. Returns the text and the columns of
+/// the start and end of the given byte range in it.
+fn underline(source: &[u8], range: &Range<usize>) -> (String, usize, usize) {
+    let mut text = String::new();
+    let mut start = 0;
+    let mut end = 0;
+    let mut offset = 0;
+    let mut add = |piece: &str, len: usize| {
+        let width = piece.chars().count();
+        if offset + len <= range.start {
+            start += width;
+        }
+        if offset < range.end {
+            end += width;
+        }
+        text.push_str(piece);
+        offset += len;
+    };
+
+    for chunk in source.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c.is_control() {
+                add(&c.escape_default().to_string(), c.len_utf8());
+            } else {
+                add(c.encode_utf8(&mut [0; 4]), c.len_utf8());
+            }
+        }
+        for byte in chunk.invalid() {
+            add(&format!("\\x{byte:02x}"), 1);
+        }
+    }
+
+    (text, start, end.max(start))
+}
@@ -3,35 +3,25 @@
 #![warn(clippy::pedantic, rust_2018_idioms)]
 #![allow(clippy::too_many_lines)]
 
+pub mod ast;
+mod complexity;
 mod engine;
+mod error;
+mod limits;
 mod lua;
+mod pattern;
+pub mod strict;
 
-pub use self::lua::{Capture, GSub, Match, Repl, find, gmatch, gsub, r#match};
-
-/// A pattern string parsing error.
-#[derive(Debug, Eq, thiserror::Error, PartialEq)]
-pub enum Error {
-    #[error("pattern too complex at {pos}")]
-    TooComplex { pos: usize },
-    #[error("too many captures at {pos}")]
-    TooManyCaptures { pos: usize },
-    #[error("invalid pattern capture at {pos}")]
-    InvalidPatternCapture { pos: usize },
-    #[error("missing '[' after '%f' in pattern at {pos}")]
-    IncompleteFrontier { pos: usize },
-    #[error("malformed pattern (missing arguments to '%b') at {pos}")]
-    MissingBalanceArgs { pos: usize },
-    #[error("invalid capture index %{index} at {pos}")]
-    InvalidCaptureIndex { pos: usize, index: usize },
-    #[error("malformed pattern (ends with '%') at {pos}")]
-    EndsWithPercent { pos: usize },
-    #[error("malformed pattern (missing ']') at {pos}")]
-    EndsWithoutBracket { pos: usize },
-    #[error("unfinished capture at {pos}")]
-    UnfinishedCapture { pos: usize },
-    #[error("invalid use of '%' in replacement string")]
-    InvalidReplacement,
-}
+pub use self::{
+    complexity::{Complexity, ComplexityClass, analyze_complexity},
+    error::{Error, Source, Span},
+    limits::MatchLimits,
+    lua::{
+        Capture, CaptureLocations, Captures, FindIter, GSub, Indexing, Match, Matcher, Progress,
+        Repl, Suspended, find, find_iter, gmatch, gsub, is_match, r#match,
+    },
+    pattern::{Pattern, validate, validate_all},
+};
 
 /// The standard [`Result`](core::result::Result) type used by lsonar.
 pub type Result<T, E = Error> = core::result::Result<T, E>;
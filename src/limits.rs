@@ -0,0 +1,169 @@
+use crate::LUA_MAXCAPTURES;
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Instant,
+};
+
+/// Limits on how much work and memory matching a pattern can use, and when
+/// it must stop.
+///
+/// The defaults match Lua. Stricter limits bound the cost of running
+/// untrusted patterns, and a looser depth lets trusted patterns backtrack
+/// further. Set them on a compiled pattern with [`Pattern::with_limits`].
+///
+/// ```
+/// # use lsonar::{MatchLimits, Pattern};
+/// # use std::time::{Duration, Instant};
+/// let limits = MatchLimits::new()
+///     .with_max_steps(10_000)
+///     .with_max_captures(4)
+///     .with_deadline(Instant::now() + Duration::from_millis(50));
+/// let pattern = Pattern::new(b"(%a+)=(%d+)")?.with_limits(limits);
+/// # Ok::<_, lsonar::Error>(())
+/// ```
+///
+/// [`Pattern::with_limits`]: crate::Pattern::with_limits
+#[derive(Clone, Debug)]
+pub struct MatchLimits {
+    depth: usize,
+    steps: Option<u64>,
+    captures: usize,
+    cancel_flag: Option<Arc<AtomicBool>>,
+    deadline: Option<Instant>,
+}
+
+impl MatchLimits {
+    /// Creates the default limits, which are the same as Lua's.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            depth: DEFAULT_MAX_DEPTH,
+            steps: None,
+            captures: LUA_MAXCAPTURES,
+            cancel_flag: None,
+            deadline: None,
+        }
+    }
+
+    /// Sets how deep the matcher can backtrack before it gives up with
+    /// [`Error::TooComplex`](crate::Error::TooComplex). This is the number of
+    /// points the matcher can backtrack to at once, such as items with a `?`
+    /// or `-` quantifier and captures. The default is 500.
+    ///
+    /// The backtracking points are kept on the heap, so the depth only
+    /// limits how much memory a match uses, which is about 40 bytes for each
+    /// level, and not how much of the native stack of the thread it uses.
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.depth = max_depth;
+        self
+    }
+
+    /// Returns how deep the matcher can backtrack before it gives up.
+    #[must_use]
+    pub fn max_depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Sets how many steps a call can take before it gives up with
+    /// [`Error::StepLimitExceeded`](crate::Error::StepLimitExceeded). By
+    /// default, there is no limit.
+    ///
+    /// A step is one item that the backtracking matcher tries at a position
+    /// of the input, including each retry after backtracking, or one byte of
+    /// the input that the automata read for each of their states. Functions
+    /// which search many times, like [`Pattern::gsub`](crate::Pattern::gsub),
+    /// [`Pattern::gmatch`](crate::Pattern::gmatch) and
+    /// [`Pattern::find_iter`](crate::Pattern::find_iter), share the budget
+    /// between all of their searches, and so does a
+    /// [`GSub`](crate::GSub) engine.
+    #[must_use]
+    pub fn with_max_steps(mut self, max_steps: u64) -> Self {
+        self.steps = Some(max_steps);
+        self
+    }
+
+    /// Returns how many steps a call can take, or `None` if there is no
+    /// limit.
+    #[must_use]
+    pub fn max_steps(&self) -> Option<u64> {
+        self.steps
+    }
+
+    /// Sets how many captures a match can open before it fails with
+    /// [`Error::TooManyCaptures`](crate::Error::TooManyCaptures). Like Lua,
+    /// the error is only reported once matching reaches the capture. The
+    /// default, and the most that a pattern can have, is
+    /// [`LUA_MAXCAPTURES`], so larger values have no effect.
+    #[must_use]
+    pub fn with_max_captures(mut self, max_captures: usize) -> Self {
+        self.captures = max_captures.min(LUA_MAXCAPTURES);
+        self
+    }
+
+    /// Returns how many captures a match can open.
+    #[must_use]
+    pub fn max_captures(&self) -> usize {
+        self.captures
+    }
+
+    /// Sets a flag which cancels matching with
+    /// [`Error::Cancelled`](crate::Error::Cancelled) once it is set, for
+    /// example by another thread.
+    ///
+    /// The matchers check the flag before each search for a match and
+    /// periodically while searching, so they stop shortly after it is set.
+    /// Functions which search many times, like
+    /// [`Pattern::gsub`](crate::Pattern::gsub), stop at the next check too.
+    #[must_use]
+    pub fn with_cancel_flag(mut self, cancel_flag: Arc<AtomicBool>) -> Self {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// Returns the flag which cancels matching, if there is one.
+    #[must_use]
+    pub fn cancel_flag(&self) -> Option<&Arc<AtomicBool>> {
+        self.cancel_flag.as_ref()
+    }
+
+    /// Sets a time after which matching gives up with
+    /// [`Error::DeadlineExceeded`](crate::Error::DeadlineExceeded). It is
+    /// checked as often as the cancellation flag.
+    #[must_use]
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the time after which matching gives up, if there is one.
+    #[must_use]
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+}
+
+impl PartialEq for MatchLimits {
+    fn eq(&self, other: &Self) -> bool {
+        // Flags are the same if they are shared
+        self.depth == other.depth
+            && self.steps == other.steps
+            && self.captures == other.captures
+            && match (&self.cancel_flag, &other.cancel_flag) {
+                (Some(flag), Some(other)) => Arc::ptr_eq(flag, other),
+                (flag, other) => flag.is_none() && other.is_none(),
+            }
+            && self.deadline == other.deadline
+    }
+}
+
+impl Eq for MatchLimits {}
+
+impl Default for MatchLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The default maximum recursion depth of the equivalent recursive matcher.
+const DEFAULT_MAX_DEPTH: usize = 500;
@@ -0,0 +1,164 @@
+use super::Indexing;
+use crate::engine::MatchRanges;
+use std::{borrow::Cow, ops::Index, ops::Range};
+
+/// A captured value.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Capture<'a> {
+    /// A substring capture `(...)`, or the whole match when a pattern has no
+    /// captures.
+    Str(&'a [u8]),
+    /// A current string position capture `()`, numbered according to the
+    /// [`Indexing`] of the match.
+    Position(usize),
+}
+
+impl<'a> Capture<'a> {
+    /// Returns the captured substring, or `None` for a position capture.
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::Str(s) => Some(s),
+            Self::Position(_) => None,
+        }
+    }
+
+    /// Returns the captured position, or `None` for a substring capture.
+    #[must_use]
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            Self::Str(_) => None,
+            Self::Position(at) => Some(*at),
+        }
+    }
+
+    /// Converts the capture to a string the same way that Lua does when a
+    /// capture is used as a string, so a position capture becomes its decimal
+    /// representation.
+    #[must_use]
+    pub fn to_lua_bytes(&self) -> Cow<'a, [u8]> {
+        match self {
+            Self::Str(s) => Cow::Borrowed(s),
+            Self::Position(at) => Cow::Owned(at.to_string().into_bytes()),
+        }
+    }
+}
+
+impl<'a> From<&'a [u8]> for Capture<'a> {
+    fn from(value: &'a [u8]) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<'a, const N: usize> From<&'a [u8; N]> for Capture<'a> {
+    fn from(value: &'a [u8; N]) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<'a> From<Capture<'a>> for Cow<'a, [u8]> {
+    fn from(value: Capture<'a>) -> Self {
+        value.to_lua_bytes()
+    }
+}
+
+/// The captures of a single match.
+///
+/// Like the captures of a regular expression, index 0 is the whole match and
+/// the captures of the pattern start at index 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Captures<'a> {
+    /// The whole match followed by each capture.
+    captures: Vec<Capture<'a>>,
+    /// The 0-based ranges of `captures` in the subject string. The range of a
+    /// position capture is empty.
+    ranges: Vec<Range<usize>>,
+}
+
+impl<'a> Captures<'a> {
+    pub(crate) fn new(subject: &'a [u8], ranges: MatchRanges, indexing: Indexing) -> Self {
+        let MatchRanges {
+            full_match,
+            captures,
+        } = ranges;
+        let mut values = Vec::with_capacity(captures.len() + 1);
+        let mut ranges = Vec::with_capacity(captures.len() + 1);
+        values.push(Capture::Str(&subject[full_match.clone()]));
+        ranges.push(full_match);
+        for capture in captures {
+            ranges.push(capture.range());
+            values.push(capture.into_capture(subject, indexing));
+        }
+        Self {
+            captures: values,
+            ranges,
+        }
+    }
+
+    /// Returns the capture at the given index, or `None` if there is no such
+    /// capture. Index 0 is the whole match.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<Capture<'a>> {
+        self.captures.get(index).copied()
+    }
+
+    /// Returns the 0-based byte range in the subject string of the capture at
+    /// the given index, or `None` if there is no such capture. Index 0 is the
+    /// whole match. The range of a position capture is empty.
+    #[must_use]
+    pub fn range(&self, index: usize) -> Option<Range<usize>> {
+        self.ranges.get(index).cloned()
+    }
+
+    /// Returns the number of captures, including the whole match.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.captures.len()
+    }
+
+    /// Always returns `false`, since the whole match is always present.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.captures.is_empty()
+    }
+
+    /// Returns an iterator over the whole match followed by each capture.
+    #[must_use]
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Capture<'a>> + '_ {
+        self.captures.iter().copied()
+    }
+
+    /// Returns the values that Lua would return for this match from
+    /// `string.match` and `string.gmatch`: the captures if the pattern has
+    /// any, otherwise the whole match.
+    #[must_use]
+    pub fn values(&self) -> &[Capture<'a>] {
+        if self.captures.len() > 1 {
+            &self.captures[1..]
+        } else {
+            &self.captures
+        }
+    }
+}
+
+impl<'a> Index<usize> for Captures<'a> {
+    type Output = Capture<'a>;
+
+    /// Returns the capture at the given index. Index 0 is the whole match.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no capture at the given index.
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.captures[index]
+    }
+}
+
+impl<'a, 'c> IntoIterator for &'c Captures<'a> {
+    type Item = Capture<'a>;
+    type IntoIter = core::iter::Copied<core::slice::Iter<'c, Capture<'a>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.captures.iter().copied()
+    }
+}
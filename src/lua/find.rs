@@ -1,117 +1,182 @@
-use super::{Capture, calculate_start_index};
+use super::{Captures, Indexing, calculate_start_index};
 use crate::{
-    Result,
-    engine::{MatchRanges, find_first_match},
+    Pattern, Result,
+    engine::{Budget, MatchRanges, TwoWay, find_first_match},
 };
+use std::ops::Range;
 
-/// The result of a [`find`] call.
+/// The result of a [`find`] call, or one item of a [`find_iter`] iteration.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Match<'a> {
     /// The start index of the found string.
     ///
-    /// # Feature flags
-    ///
-    /// This will be 1-indexed if the `1-based` feature is enabled.
+    /// This is 1-indexed if the match used [`Indexing::OneBased`].
     pub start: usize,
     /// The end index of the found string.
     ///
-    /// # Feature flags
-    ///
-    /// This will be an inclusive index if the `1-based` feature is enabled.
+    /// This is an inclusive index if the match used [`Indexing::OneBased`].
     pub end: usize,
-    /// The captured string slices. If a capture did not result in any value,
-    /// it will be an empty slice.
-    pub captures: Vec<Capture<'a>>,
+    /// The whole match and the captures of the pattern, with their ranges.
+    pub captures: Captures<'a>,
 }
 
-// TODO: This exists only to avoid having to spend a bunch of time changing the
-// unit tests
-impl<'a> From<(usize, usize, Vec<Capture<'a>>)> for Match<'a> {
-    fn from((start, end, captures): (usize, usize, Vec<Capture<'a>>)) -> Self {
+impl<'a> Match<'a> {
+    pub(super) fn new(s: &'a [u8], ranges: MatchRanges, indexing: Indexing) -> Self {
         Self {
-            start,
-            end,
-            captures,
+            start: indexing.position(ranges.full_match.start),
+            end: ranges.full_match.end,
+            captures: Captures::new(s, ranges, indexing),
         }
     }
+
+    /// Returns the 0-based byte range of the found string, regardless of the
+    /// indexing used for `start` and `end`.
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.captures.range(0).unwrap_or_default()
+    }
 }
 
 /// Like Lua
 /// [`string.find`](https://www.lua.org/manual/5.3/manual.html#pdf-string.find),
 /// looks for the first match of `pattern` in the string `s`.
 ///
+/// If `plain` is set, or like Lua if `pattern` has no special characters, the
+/// pattern is searched for as a plain string instead.
+///
 /// # Errors
 ///
 /// If the pattern string could not be parsed, an [`Error`](crate::Error) is returned.
+/// Like Lua, a syntax error is only reported once matching reaches it; use
+/// [`strict::find`](crate::strict::find) to check the whole pattern first.
 ///
-/// # Feature flags
-///
-/// The input `init` and output `start` and `end` indices are 1-indexed if the
-/// `1-based` feature is enabled.
+/// The input `init` and output `start` and `end` indices are 1-indexed, like
+/// Lua.
 pub fn find<'a>(
     s: &'a [u8],
     pattern: &[u8],
     init: Option<isize>,
     plain: bool,
 ) -> Result<Option<Match<'a>>> {
-    let byte_len = s.len();
-
-    let start_byte_index = calculate_start_index(byte_len, init);
+    if plain || !has_specials(pattern) {
+        let start_byte_index = calculate_start_index(s.len(), init, Indexing::OneBased);
+        let Some(rest) = s.get(start_byte_index..) else {
+            return Ok(None);
+        };
 
-    if plain {
-        if pattern.is_empty() {
-            return Ok(Some(Match {
-                start: if cfg!(feature = "1-based") {
-                    start_byte_index.saturating_add(1)
-                } else {
-                    start_byte_index
+        Ok(TwoWay::new(pattern).find(rest).map(|pos| {
+            let start = start_byte_index + pos;
+            Match::new(
+                s,
+                MatchRanges {
+                    full_match: start..start + pattern.len(),
+                    captures: vec![],
                 },
-                end: start_byte_index,
-                captures: vec![],
-            }));
-        }
+                Indexing::OneBased,
+            )
+        }))
+    } else {
+        Pattern::lenient(pattern).find(s, init)
+    }
+}
 
-        if start_byte_index >= byte_len {
-            return Ok(None);
+/// Checks whether a pattern string contains any of the characters which Lua
+/// considers special. Like Lua, `find` treats a pattern without them as a
+/// plain string, even if it would be malformed as a pattern, like `a)`.
+pub(super) fn has_specials(pattern: &[u8]) -> bool {
+    pattern.iter().any(|c| b"^$*+?.([%-".contains(c))
+}
+
+/// Returns an iterator of every match of `pattern` in the string `s`, like
+/// [`gmatch`](crate::gmatch), but yielding the position of each match along
+/// with its captures, like [`find`].
+///
+/// # Errors
+///
+/// If the pattern string could not be parsed, an [`Error`](crate::Error) is returned.
+/// Like Lua, a syntax error is only reported by the iterator once matching
+/// reaches it; use [`strict::find_iter`](crate::strict::find_iter) to check
+/// the whole pattern first.
+///
+/// The input `init` and output `start` and `end` indices are 1-indexed, like
+/// Lua.
+pub fn find_iter<'a>(s: &'a [u8], pattern: &[u8], init: Option<isize>) -> Result<FindIter<'a>> {
+    Ok(Pattern::lenient(pattern).find_iter(s, init))
+}
+
+impl Pattern {
+    /// Like [`find`], looks for the first match of this pattern in the string
+    /// `s`.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is too complex to match against the string, an
+    /// [`Error`](crate::Error) is returned.
+    ///
+    /// The input `init` and output `start` and `end` indices follow the
+    /// [`Indexing`] of the pattern.
+    pub fn find<'a>(&self, s: &'a [u8], init: Option<isize>) -> Result<Option<Match<'a>>> {
+        let start_byte_index = calculate_start_index(s.len(), init, self.indexing());
+        Ok(find_first_match(
+            s,
+            self.parsed(),
+            start_byte_index,
+            &mut Budget::new(self.limits()),
+        )?
+        .map(|ranges| Match::new(s, ranges, self.indexing())))
+    }
+
+    /// Like [`find_iter`], returns an iterator of every match of this pattern
+    /// in the string `s`.
+    ///
+    /// The input `init` and output `start` and `end` indices follow the
+    /// [`Indexing`] of the pattern.
+    #[must_use]
+    pub fn find_iter<'a>(&self, s: &'a [u8], init: Option<isize>) -> FindIter<'a> {
+        FindIter {
+            bytes: s,
+            pattern: self.clone(),
+            current_pos: calculate_start_index(s.len(), init, self.indexing()),
+            budget: Budget::new(self.limits()),
         }
+    }
+}
 
-        if let Some(rel_byte_pos) = s[start_byte_index..]
-            .windows(pattern.len())
-            .position(|window| window == pattern)
-        {
-            let zero_based_start_pos = start_byte_index + rel_byte_pos;
-            let zero_based_end_pos = zero_based_start_pos + pattern.len();
+/// An iterator of every match of a pattern in a string, as returned by
+/// [`find_iter`] and [`Pattern::find_iter`].
+///
+/// Each item is a [`Match`], or an [`Error`](crate::Error) if matching
+/// failed. An empty match moves the search forward by one byte, like Lua.
+pub struct FindIter<'a> {
+    bytes: &'a [u8],
+    pattern: Pattern,
+    current_pos: usize,
+    budget: Budget,
+}
 
-            Ok(Some(Match {
-                start: if cfg!(feature = "1-based") {
-                    zero_based_start_pos.saturating_add(1)
-                } else {
-                    zero_based_start_pos
-                },
-                end: zero_based_end_pos,
-                captures: vec![],
-            }))
-        } else {
-            Ok(None)
+impl<'a> Iterator for FindIter<'a> {
+    type Item = Result<Match<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_pos > self.bytes.len() {
+            return None;
         }
-    } else {
-        match find_first_match(s, pattern, start_byte_index)? {
-            Some(MatchRanges {
-                full_match,
-                captures,
-            }) => Ok(Some(Match {
-                start: if cfg!(feature = "1-based") {
-                    full_match.start.saturating_add(1)
-                } else {
-                    full_match.start
-                },
-                end: full_match.end,
-                captures: captures
-                    .into_iter()
-                    .map(|range| range.into_bytes(s))
-                    .collect(),
-            })),
-            None => Ok(None),
+
+        match find_first_match(
+            self.bytes,
+            self.pattern.parsed(),
+            self.current_pos,
+            &mut self.budget,
+        ) {
+            Ok(result) => result.map(|ranges| {
+                self.current_pos = ranges.full_match.end;
+                if ranges.full_match.is_empty() {
+                    self.current_pos += 1;
+                }
+
+                Ok(Match::new(self.bytes, ranges, self.pattern.indexing()))
+            }),
+            Err(err) => Some(Err(err)),
         }
     }
 }
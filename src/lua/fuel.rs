@@ -0,0 +1,100 @@
+use super::{Match, calculate_start_index};
+use crate::{
+    Pattern, Result,
+    engine::{Fueled, Suspension, find_with_fuel, resume},
+};
+use std::fmt;
+
+/// The result of [`Pattern::find_with_fuel`] or [`Suspended::resume`].
+#[derive(Debug)]
+pub enum Progress<'a> {
+    /// The search finished, with the first match if there is one.
+    Done(Option<Match<'a>>),
+    /// The search ran out of fuel, and can be resumed later.
+    Suspended(Suspended<'a>),
+}
+
+/// A search which ran out of fuel, with everything the matcher needs to
+/// continue exactly where it stopped.
+pub struct Suspended<'a> {
+    s: &'a [u8],
+    pattern: Pattern,
+    suspension: Suspension,
+}
+
+impl<'a> Suspended<'a> {
+    /// Continues the search for up to `fuel` more steps, or one step if
+    /// `fuel` is 0.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is too complex to match against the string, an
+    /// [`Error`](crate::Error) is returned.
+    pub fn resume(self, fuel: u64) -> Result<Progress<'a>> {
+        let Self {
+            s,
+            pattern,
+            suspension,
+        } = self;
+        let fueled = resume(s, pattern.parsed(), pattern.limits(), suspension, fuel)?;
+        Ok(progress(s, pattern, fueled))
+    }
+}
+
+impl fmt::Debug for Suspended<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Suspended")
+            .field("pattern", &self.pattern)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Pattern {
+    /// Like [`Pattern::find`], looks for the first match of this pattern in
+    /// the string `s`, but stops after `fuel` steps so that a long search
+    /// does not block other work, like other tasks of an event loop.
+    ///
+    /// If the search runs out of fuel, [`Progress::Suspended`] is returned,
+    /// which continues the search with [`Suspended::resume`]. However many
+    /// times it is suspended, the result is the same as that of
+    /// [`Pattern::find`]. A fuel of 0 is taken as 1, so that every call makes
+    /// progress.
+    ///
+    /// Only the backtracking matcher can stop and continue, so the fuel
+    /// counts its steps, even for patterns which [`Pattern::find`] matches
+    /// with automata. Those steps also count towards
+    /// [`MatchLimits::with_max_steps`](crate::MatchLimits::with_max_steps),
+    /// which limits the whole search, across every time it is resumed.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is too complex to match against the string, an
+    /// [`Error`](crate::Error) is returned.
+    ///
+    /// The input `init` and output `start` and `end` indices follow the
+    /// [`Indexing`](crate::Indexing) of the pattern.
+    pub fn find_with_fuel<'a>(
+        &self,
+        s: &'a [u8],
+        init: Option<isize>,
+        fuel: u64,
+    ) -> Result<Progress<'a>> {
+        let start_byte_index = calculate_start_index(s.len(), init, self.indexing());
+        let fueled = find_with_fuel(s, self.parsed(), start_byte_index, self.limits(), fuel)?;
+        Ok(progress(s, self.clone(), fueled))
+    }
+}
+
+/// Converts the result of a search with a limited amount of fuel.
+fn progress(s: &[u8], pattern: Pattern, fueled: Fueled) -> Progress<'_> {
+    match fueled {
+        Fueled::Done(ranges) => {
+            Progress::Done(ranges.map(|ranges| Match::new(s, ranges, pattern.indexing())))
+        }
+        Fueled::Suspended(suspension) => Progress::Suspended(Suspended {
+            s,
+            pattern,
+            suspension,
+        }),
+    }
+}
@@ -1,10 +1,5 @@
-use super::calculate_start_index;
-use crate::{
-    Result,
-    engine::{MatchRanges, find_first_match},
-    lua::Capture,
-};
-use std::borrow::Cow;
+use super::{Captures, FindIter};
+use crate::{Pattern, Result};
 
 /// Like Lua
 /// [`string.gmatch`](https://www.lua.org/manual/5.3/manual.html#pdf-string.gmatch),
@@ -13,58 +8,33 @@ use std::borrow::Cow;
 /// # Errors
 ///
 /// If the pattern string could not be parsed, an [`Error`](crate::Error) is returned.
+/// Like Lua, a syntax error is only reported by the iterator once matching
+/// reaches it; use [`strict::gmatch`](crate::strict::gmatch) to check the
+/// whole pattern first.
 ///
-/// # Feature flags
-///
-/// Captured string positions are 1-indexed if the `1-based` feature is enabled.
-pub fn gmatch<'a>(
-    s: &'a [u8],
-    pattern: &'a [u8],
-    init: Option<isize>,
-) -> Result<GMatchIterator<'a>> {
-    Ok(GMatchIterator {
-        bytes: s,
-        pattern,
-        current_pos: calculate_start_index(s.len(), init),
-    })
+/// The input `init` index and position captures are 1-indexed, like Lua.
+pub fn gmatch<'a>(s: &'a [u8], pattern: &[u8], init: Option<isize>) -> Result<GMatchIterator<'a>> {
+    Ok(Pattern::lenient(pattern).gmatch(s, init))
 }
 
-pub struct GMatchIterator<'a> {
-    pub(super) bytes: &'a [u8],
-    pub(super) pattern: &'a [u8],
-    pub(super) current_pos: usize,
+impl Pattern {
+    /// Like [`gmatch`], returns an iterator of the captures of this pattern
+    /// over the string `s`.
+    ///
+    /// The input `init` index and position captures follow the
+    /// [`Indexing`](crate::Indexing) of the pattern.
+    #[must_use]
+    pub fn gmatch<'a>(&self, s: &'a [u8], init: Option<isize>) -> GMatchIterator<'a> {
+        GMatchIterator(self.find_iter(s, init))
+    }
 }
 
+pub struct GMatchIterator<'a>(FindIter<'a>);
+
 impl<'a> Iterator for GMatchIterator<'a> {
-    type Item = Result<Vec<Capture<'a>>>;
+    type Item = Result<Captures<'a>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_pos > self.bytes.len() {
-            return None;
-        }
-
-        match find_first_match(self.bytes, self.pattern, self.current_pos) {
-            Ok(result) => result.map(
-                |MatchRanges {
-                     full_match,
-                     captures,
-                 }| {
-                    self.current_pos = full_match.end;
-                    if full_match.is_empty() {
-                        self.current_pos += 1;
-                    }
-
-                    Ok(if captures.is_empty() {
-                        vec![Cow::Borrowed(&self.bytes[full_match])]
-                    } else {
-                        captures
-                            .into_iter()
-                            .map(|range| range.into_bytes(self.bytes))
-                            .collect()
-                    })
-                },
-            ),
-            Err(err) => Some(Err(err)),
-        }
+        self.0.next().map(|m| m.map(|m| m.captures))
     }
 }
@@ -1,9 +1,9 @@
-use super::Capture;
+use super::{Capture, Captures};
 use crate::{
-    Error, Result,
-    engine::{CaptureRange, find_first_match},
+    Error, Pattern, Result, Span,
+    engine::{Budget, find_first_match},
 };
-use std::{borrow::Cow, ops::Range};
+use std::ops::Range;
 
 /// A piecewise text substitution engine.
 ///
@@ -11,13 +11,14 @@ use std::{borrow::Cow, ops::Range};
 /// allows for iterative replacement of strings by separating the matching and
 /// replacing parts.
 pub struct GSub {
-    pattern: Vec<u8>,
+    pattern: Pattern,
     replacements: usize,
     found: usize,
     result: Vec<u8>,
     last_pos: usize,
     last_replace: usize,
     current: Range<usize>,
+    budget: Budget,
 }
 
 impl GSub {
@@ -26,17 +27,26 @@ impl GSub {
     /// # Errors
     ///
     /// If the pattern string could not be parsed, an [`Error`](crate::Error) is
-    /// returned.
+    /// returned. Like Lua, a syntax error is only reported by [`GSub::next`]
+    /// once matching reaches it; use [`GSub::from_pattern`] with
+    /// [`Pattern::new`] to check the whole pattern first.
     pub fn new(pattern: &[u8], n: Option<usize>) -> Result<Self> {
-        Ok(Self {
-            pattern: pattern.to_vec(),
+        Ok(Self::from_pattern(Pattern::lenient(pattern), n))
+    }
+
+    /// Creates a new substitution engine for a compiled pattern.
+    #[must_use]
+    pub fn from_pattern(pattern: Pattern, n: Option<usize>) -> Self {
+        Self {
+            budget: Budget::new(pattern.limits()),
+            pattern,
             replacements: n.unwrap_or(usize::MAX),
             found: 0,
             result: Vec::new(),
             last_pos: 0,
             last_replace: usize::MAX,
             current: 0..0,
-        })
+        }
     }
 
     /// Returns the final string and the number of replacements, consuming the
@@ -55,15 +65,20 @@ impl GSub {
     ///
     /// If a syntax error is encountered in the pattern string, an [`Error`] is
     /// returned.
-    pub fn next<'a>(&mut self, input: &'a [u8]) -> Result<Option<(Capture<'a>, Vec<Capture<'a>>)>> {
+    pub fn next<'a>(&mut self, input: &'a [u8]) -> Result<Option<Captures<'a>>> {
         Ok(
             if self.replacements > 0
-                && let Some(ranges) = find_first_match(input, &self.pattern, self.last_pos)?
+                && let Some(ranges) = find_first_match(
+                    input,
+                    self.pattern.parsed(),
+                    self.last_pos,
+                    &mut self.budget,
+                )?
             {
                 self.found += 1;
                 self.replacements -= 1;
-                self.current = ranges.full_match;
-                Some(self.captures(input, &ranges.captures))
+                self.current = ranges.full_match.clone();
+                Some(Captures::new(input, ranges, self.pattern.indexing()))
             } else {
                 None
             },
@@ -96,20 +111,6 @@ impl GSub {
             }
         }
     }
-
-    fn captures<'a>(
-        &self,
-        input: &'a [u8],
-        captures: &[CaptureRange],
-    ) -> (Capture<'a>, Vec<Capture<'a>>) {
-        (
-            Cow::Borrowed(&input[self.current.clone()]),
-            captures
-                .iter()
-                .map(|range| range.clone().into_bytes(input))
-                .collect::<Vec<_>>(),
-        )
-    }
 }
 
 /// Like Lua
@@ -120,34 +121,44 @@ impl GSub {
 /// # Errors
 ///
 /// If the pattern string could not be parsed, an [`Error`](crate::Error) is returned.
+/// Like Lua, a syntax error is only reported once matching reaches it; use
+/// [`strict::gsub`](crate::strict::gsub) to check the whole pattern first.
 pub fn gsub<'a>(
     s: &'a [u8],
     pattern: &[u8],
-    mut repl: Repl<'a>,
+    repl: Repl<'a>,
     n: Option<usize>,
 ) -> Result<(Vec<u8>, usize)> {
-    let mut generator = GSub::new(pattern, n)?;
-    while let Some((ref full_match, rest)) = generator.next(s)? {
-        let replacement = match &mut repl {
-            Repl::String(repl_str) => {
-                Some(process_replacement_string(repl_str, full_match, &rest)?)
-            }
-            Repl::Function(f) => {
-                let full_match = core::slice::from_ref(full_match);
-                f(if rest.is_empty() { full_match } else { &rest })
-            }
-            Repl::Table(f) => {
-                let key = rest.first().unwrap_or(full_match);
-                f(key.clone())
-            }
-        };
-        generator.replace(s, replacement.as_deref());
-    }
-
-    Ok(generator.finish(s))
+    Pattern::lenient(pattern).gsub(s, repl, n)
 }
 
-type Key<'a> = Cow<'a, [u8]>;
+impl Pattern {
+    /// Like [`gsub`], returns a copy of `s` in which all (or the first `n`, if
+    /// given) occurrences of this pattern are replaced by `repl`.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is too complex to match against the string, or the
+    /// replacement string is invalid, an [`Error`](crate::Error) is returned.
+    pub fn gsub<'a>(
+        &self,
+        s: &'a [u8],
+        mut repl: Repl<'a>,
+        n: Option<usize>,
+    ) -> Result<(Vec<u8>, usize)> {
+        let mut generator = GSub::from_pattern(self.clone(), n);
+        while let Some(captures) = generator.next(s)? {
+            let replacement = match &mut repl {
+                Repl::String(repl_str) => Some(process_replacement_string(repl_str, &captures)?),
+                Repl::Function(f) => f(captures.values()),
+                Repl::Table(f) => f(captures.values()[0]),
+            };
+            generator.replace(s, replacement.as_deref());
+        }
+
+        Ok(generator.finish(s))
+    }
+}
 
 /// The string replacement strategy to use with [`gsub`](crate::gsub).
 pub enum Repl<'a> {
@@ -157,24 +168,21 @@ pub enum Repl<'a> {
     /// the sequence `%0` stands for the whole match; the sequence `%%` stands
     /// for a single `%`.
     String(&'a [u8]),
-    /// This function is called every time a match occurs, with all captured
-    /// substrings passed as a slice, in order.
+    /// This function is called every time a match occurs, with all captures
+    /// passed as a slice, in order.
     Function(&'a mut dyn FnMut(&[Capture<'_>]) -> Option<Vec<u8>>),
     /// This function is queried for every match, using the first capture as the
     /// key.
-    Table(&'a dyn Fn(Key<'_>) -> Option<Vec<u8>>),
+    Table(&'a dyn Fn(Capture<'_>) -> Option<Vec<u8>>),
 }
 
 enum ReplToken {
     Literal(u8),
-    CaptureRef(u8),
+    /// A capture reference and its position in the replacement string.
+    CaptureRef(u8, usize),
 }
 
-fn process_replacement_string(
-    repl: &[u8],
-    full_match: &Capture<'_>,
-    captures: &[Capture<'_>],
-) -> Result<Vec<u8>> {
+fn process_replacement_string(repl: &[u8], captures: &Captures<'_>) -> Result<Vec<u8>> {
     let tokens = tokenize_replacement_string(repl)?;
     let mut result = Vec::with_capacity(tokens.len());
 
@@ -183,14 +191,17 @@ fn process_replacement_string(
             ReplToken::Literal(b) => {
                 result.push(b);
             }
-            ReplToken::CaptureRef(idx) => {
+            ReplToken::CaptureRef(idx, pos) => {
                 let idx = usize::from(idx);
-                if idx == 0 || (idx == 1 && captures.is_empty()) {
-                    result.extend(full_match.as_ref());
-                } else if idx <= captures.len() {
-                    result.extend(captures[idx - 1].as_ref());
+                if idx == 1 && captures.len() == 1 {
+                    result.extend(captures[0].to_lua_bytes().as_ref());
+                } else if let Some(capture) = captures.get(idx) {
+                    result.extend(capture.to_lua_bytes().as_ref());
                 } else {
-                    return Err(Error::InvalidCaptureIndex { pos: 0, index: idx });
+                    return Err(Error::InvalidCaptureIndex {
+                        span: Span::replacement(pos..pos + 2),
+                        index: idx,
+                    });
                 }
             }
         }
@@ -206,9 +217,15 @@ fn tokenize_replacement_string(repl: &[u8]) -> Result<Vec<ReplToken>> {
     while i < repl.len() {
         if repl[i] == b'%' && i + 1 < repl.len() {
             tokens.push(match repl[i + 1] {
-                next_byte if next_byte.is_ascii_digit() => ReplToken::CaptureRef(next_byte - b'0'),
+                next_byte if next_byte.is_ascii_digit() => {
+                    ReplToken::CaptureRef(next_byte - b'0', i)
+                }
                 next_byte @ b'%' => ReplToken::Literal(next_byte),
-                _ => return Err(Error::InvalidReplacement),
+                _ => {
+                    return Err(Error::InvalidReplacement {
+                        span: Span::replacement(i..i + 2),
+                    });
+                }
             });
             i += 2;
         } else {
@@ -0,0 +1,66 @@
+use super::{Indexing, calculate_start_index, find::has_specials};
+use crate::{
+    Pattern, Result,
+    engine::{Budget, TwoWay, has_direct_match, has_match},
+};
+
+/// Checks whether `pattern` matches anywhere in the string `s`.
+///
+/// This is like checking whether [`find`](crate::find) returns `Some`, but the
+/// matcher stops at the first success and never builds the captures. Like
+/// `find`, a pattern without special characters is searched for as a plain
+/// string.
+///
+/// The pattern string is matched directly, without being compiled, so nothing
+/// is allocated. Only if the matcher reaches a syntax error, or has to
+/// backtrack a lot, is the pattern compiled and matched by the full engine
+/// instead, which allocates. Use [`Pattern::is_match`] to compile `pattern`
+/// only once.
+///
+/// # Errors
+///
+/// If the pattern string could not be parsed, an [`Error`](crate::Error) is returned.
+/// Like Lua, a syntax error is only reported once matching reaches it; use
+/// [`strict::is_match`](crate::strict::is_match) to check the whole pattern
+/// first.
+///
+/// The input `init` index is 1-indexed, like Lua.
+pub fn is_match(s: &[u8], pattern: &[u8], init: Option<isize>) -> Result<bool> {
+    let start_byte_index = calculate_start_index(s.len(), init, Indexing::OneBased);
+    if has_specials(pattern) {
+        if let Some(matched) = has_direct_match(s, pattern, start_byte_index) {
+            return Ok(matched);
+        }
+        Pattern::lenient(pattern).is_match(s, init)
+    } else {
+        Ok(s.get(start_byte_index..)
+            .is_some_and(|rest| TwoWay::new(pattern).find(rest).is_some()))
+    }
+}
+
+impl Pattern {
+    /// Like [`is_match`], checks whether this pattern matches anywhere in the
+    /// string `s`.
+    ///
+    /// Most patterns are matched by an automaton, which allocates nothing
+    /// apart from growing an internal cache of the pattern the first times it
+    /// is used. Patterns which it cannot match, like those with
+    /// back-references, allocate working memory for each search.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is too complex to match against the string, an
+    /// [`Error`](crate::Error) is returned.
+    ///
+    /// The input `init` index follows the [`Indexing`](crate::Indexing) of
+    /// the pattern.
+    pub fn is_match(&self, s: &[u8], init: Option<isize>) -> Result<bool> {
+        let start_byte_index = calculate_start_index(s.len(), init, self.indexing());
+        has_match(
+            s,
+            self.parsed(),
+            start_byte_index,
+            &mut Budget::new(self.limits()),
+        )
+    }
+}
@@ -1,46 +1,48 @@
-use super::{Capture, calculate_start_index};
+use super::{Captures, calculate_start_index};
 use crate::{
-    Result,
-    engine::{MatchRanges, find_first_match},
+    Pattern, Result,
+    engine::{Budget, find_first_match},
 };
-use std::borrow::Cow;
 
 /// Like Lua
 /// [`string.match`](https://www.lua.org/manual/5.3/manual.html#pdf-string.match),
-/// looks for the first match of `pattern` in the string `s`.
+/// looks for the first match of `pattern` in the string `s`. Returns `None` if
+/// there is no match.
 ///
 /// # Errors
 ///
 /// If the pattern string could not be parsed, an [`Error`](crate::Error) is returned.
+/// Like Lua, a syntax error is only reported once matching reaches it; use
+/// `strict::r#match` to check the whole pattern first.
 ///
-/// # Feature flags
-///
-/// The input `init` index is 1-indexed if the `1-based` feature is enabled.
+/// The input `init` index and position captures are 1-indexed, like Lua.
 pub fn r#match<'a>(
     text: &'a [u8],
     pattern: &[u8],
     init: Option<isize>,
-) -> Result<Vec<Capture<'a>>> {
-    let byte_len = text.len();
-
-    let start_byte_index = calculate_start_index(byte_len, init);
-
-    Ok(match find_first_match(text, pattern, start_byte_index)? {
-        Some(MatchRanges {
-            full_match,
-            captures,
-        }) => {
-            let has_captures = !captures.is_empty();
+) -> Result<Option<Captures<'a>>> {
+    Pattern::lenient(pattern).r#match(text, init)
+}
 
-            if has_captures {
-                captures
-                    .into_iter()
-                    .map(|range| range.into_bytes(text))
-                    .collect()
-            } else {
-                vec![Cow::Borrowed(&text[full_match])]
-            }
-        }
-        None => vec![],
-    })
+impl Pattern {
+    /// Like `r#match`, looks for the first match of this pattern in the
+    /// string `text`. Returns `None` if there is no match.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is too complex to match against the string, an
+    /// [`Error`](crate::Error) is returned.
+    ///
+    /// The input `init` index and position captures follow the
+    /// [`Indexing`](crate::Indexing) of the pattern.
+    pub fn r#match<'a>(&self, text: &'a [u8], init: Option<isize>) -> Result<Option<Captures<'a>>> {
+        let start_byte_index = calculate_start_index(text.len(), init, self.indexing());
+        Ok(find_first_match(
+            text,
+            self.parsed(),
+            start_byte_index,
+            &mut Budget::new(self.limits()),
+        )?
+        .map(|ranges| Captures::new(text, ranges, self.indexing())))
+    }
 }
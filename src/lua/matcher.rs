@@ -0,0 +1,155 @@
+use super::{Capture, Indexing, calculate_start_index};
+use crate::{
+    LUA_MAXCAPTURES, Pattern, Result,
+    engine::{Budget, CaptureRange, Scratch, find_into},
+};
+use std::{fmt, ops::Range};
+
+/// Memory for matching which is kept between matches.
+///
+/// Each match needs some working memory, which the matching functions
+/// allocate and free again every time they are called. To match many times
+/// without allocating, keep a `Matcher`, for example one per thread, and pass
+/// it to [`Pattern::captures_read_at`] together with a [`CaptureLocations`].
+/// Once the memory has grown as large as the matches need, nothing is
+/// allocated anymore.
+pub struct Matcher {
+    scratch: Scratch,
+}
+
+impl Matcher {
+    /// Creates a matcher which has not allocated any memory yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            scratch: Scratch::new(),
+        }
+    }
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Matcher").finish_non_exhaustive()
+    }
+}
+
+/// The locations of the whole match and the captures of a match, which
+/// [`Pattern::captures_read_at`] writes into.
+///
+/// Like [`Captures`](crate::Captures), index 0 is the whole match and the
+/// captures of the pattern start at index 1. After a failed match, there are
+/// no locations at all.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CaptureLocations {
+    /// The 0-based range of the whole match, if there is one.
+    full_match: Option<Range<usize>>,
+    /// The captures of the pattern.
+    captures: Vec<CaptureRange>,
+    /// The indexing of the position captures.
+    indexing: Indexing,
+}
+
+impl CaptureLocations {
+    /// Creates an empty set of locations, with room for as many captures as
+    /// a pattern can have.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            full_match: None,
+            captures: Vec::with_capacity(LUA_MAXCAPTURES),
+            indexing: Indexing::default(),
+        }
+    }
+
+    /// Returns the 0-based byte range in the subject string of the capture at
+    /// the given index, or `None` if there is no such capture. Index 0 is the
+    /// whole match. The range of a position capture is empty.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<Range<usize>> {
+        match index {
+            0 => self.full_match.clone(),
+            _ => self.captures.get(index - 1).map(CaptureRange::range),
+        }
+    }
+
+    /// Returns the capture at the given index in the subject string that was
+    /// matched, or `None` if there is no such capture. Index 0 is the whole
+    /// match. Position captures are numbered according to the [`Indexing`] of
+    /// the pattern.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is shorter than the subject string that was matched.
+    #[must_use]
+    pub fn capture<'a>(&self, index: usize, s: &'a [u8]) -> Option<Capture<'a>> {
+        match index {
+            0 => self.full_match.clone().map(|range| Capture::Str(&s[range])),
+            _ => self
+                .captures
+                .get(index - 1)
+                .map(|capture| capture.clone().into_capture(s, self.indexing)),
+        }
+    }
+
+    /// Returns the number of locations, including the whole match, which is
+    /// 0 after a failed match.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.full_match
+            .as_ref()
+            .map_or(0, |_| self.captures.len() + 1)
+    }
+
+    /// Returns `true` after a failed match.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.full_match.is_none()
+    }
+}
+
+impl Pattern {
+    /// Like [`Pattern::find`], looks for the first match of this pattern in
+    /// the string `s`, but writes the locations of the match into `locations`
+    /// instead of returning them, and works in the memory of `matcher`. Once
+    /// both have grown large enough, nothing is allocated.
+    ///
+    /// Returns the 0-based byte range of the whole match if there is one.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern is too complex to match against the string, an
+    /// [`Error`](crate::Error) is returned.
+    ///
+    /// The input `init` index and position captures follow the [`Indexing`]
+    /// of the pattern.
+    pub fn captures_read_at(
+        &self,
+        matcher: &mut Matcher,
+        locations: &mut CaptureLocations,
+        s: &[u8],
+        init: Option<isize>,
+    ) -> Result<Option<Range<usize>>> {
+        let start_byte_index = calculate_start_index(s.len(), init, self.indexing());
+        locations.full_match = None;
+        locations.indexing = self.indexing();
+        let full_match = find_into(
+            s,
+            self.parsed(),
+            start_byte_index,
+            &mut Budget::new(self.limits()),
+            &mut matcher.scratch,
+            &mut locations.captures,
+        )?;
+        if full_match.is_none() {
+            locations.captures.clear();
+        }
+        locations.full_match.clone_from(&full_match);
+        Ok(full_match)
+    }
+}
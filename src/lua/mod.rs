@@ -1,23 +1,58 @@
+mod captures;
 mod find;
+mod fuel;
 mod gmatch;
 mod gsub;
+mod is_match;
 mod r#match;
+mod matcher;
 
 pub use self::{
-    find::{Match, find},
-    gmatch::gmatch,
+    captures::{Capture, Captures},
+    find::{FindIter, Match, find, find_iter},
+    fuel::{Progress, Suspended},
+    gmatch::{GMatchIterator, gmatch},
     gsub::{GSub, Repl, gsub},
+    is_match::is_match,
     r#match::r#match,
+    matcher::{CaptureLocations, Matcher},
 };
-pub use std::borrow::Cow;
 
-/// The type of a captured string.
-pub type Capture<'a> = Cow<'a, [u8]>;
+/// How string positions are numbered in the arguments and results of the
+/// matching functions.
+///
+/// The free functions always use Lua's 1-based indexing. A [`Pattern`] can be
+/// set to use 0-based indexing instead with [`Pattern::with_indexing`].
+///
+/// [`Pattern`]: crate::Pattern
+/// [`Pattern::with_indexing`]: crate::Pattern::with_indexing
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Indexing {
+    /// Positions start at 1 and the end of a match is inclusive, like in Lua.
+    #[default]
+    OneBased,
+    /// Positions start at 0 and the end of a match is exclusive, like in Rust
+    /// ranges.
+    ZeroBased,
+}
+
+impl Indexing {
+    /// Converts a 0-based string position to this indexing.
+    pub(crate) fn position(self, at: usize) -> usize {
+        match self {
+            Self::OneBased => at.saturating_add(1),
+            Self::ZeroBased => at,
+        }
+    }
+}
 
-fn calculate_start_index(text_len: usize, init: Option<isize>) -> usize {
+fn calculate_start_index(text_len: usize, init: Option<isize>, indexing: Indexing) -> usize {
     match init {
         Some(i) if i > 0 => {
-            let i = if cfg!(feature = "1-based") { i - 1 } else { i };
+            let i = match indexing {
+                Indexing::OneBased => i - 1,
+                Indexing::ZeroBased => i,
+            };
             // Clippy: Precondition `i > 0` guarantees no sign loss
             #[allow(clippy::cast_sign_loss)]
             {
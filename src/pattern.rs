@@ -0,0 +1,123 @@
+use crate::{
+    Error, Indexing, MatchLimits, Result,
+    ast::{self, Parsed},
+};
+use std::sync::Arc;
+
+/// Checks a pattern string for syntax errors without compiling it for
+/// matching.
+///
+/// This catches every malformed pattern, including errors in parts of the
+/// pattern that the matcher would never reach for a given input.
+///
+/// # Errors
+///
+/// If the pattern string is malformed, the first [`Error`](crate::Error) in it
+/// is returned.
+pub fn validate(pattern: &[u8]) -> Result<()> {
+    Parsed::new(pattern).error().map_or(Ok(()), Err)
+}
+
+/// Checks a pattern string for every syntax error in it.
+///
+/// Unlike [`validate`], which stops at the first error, this keeps checking
+/// the rest of the pattern after each error, so that all of the problems in it
+/// can be reported at once.
+///
+/// # Errors
+///
+/// If the pattern string is malformed, every [`Error`](crate::Error) in it is
+/// returned, in the order that they appear in the pattern.
+pub fn validate_all(pattern: &[u8]) -> Result<(), Vec<Error>> {
+    let errors = ast::errors(pattern);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// A compiled pattern.
+///
+/// The free functions like [`find`](crate::find) parse the pattern string
+/// again every time they are called. When the same pattern is used many times,
+/// compile it once with [`Pattern::new`] and use its methods instead.
+///
+/// A `Pattern` is cheap to clone and can be shared between threads.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    parsed: Arc<Parsed>,
+    indexing: Indexing,
+    limits: MatchLimits,
+}
+
+impl Pattern {
+    /// Compiles a pattern string.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern string is malformed, an [`Error`](crate::Error) is
+    /// returned. Unlike the free functions, which like Lua only report a
+    /// syntax error once matching reaches it, this rejects every malformed
+    /// pattern up front.
+    pub fn new(pattern: &[u8]) -> Result<Self> {
+        let parsed = Parsed::new(pattern);
+        if let Some(error) = parsed.error() {
+            return Err(error);
+        }
+        Ok(Self {
+            parsed: Arc::new(parsed),
+            indexing: Indexing::default(),
+            limits: MatchLimits::new(),
+        })
+    }
+
+    /// Compiles a pattern string without checking it for syntax errors. Any
+    /// error is reported once matching reaches it, the same as Lua does. The
+    /// pattern is meant for a single call, so its automata are only built if
+    /// the input is very long.
+    pub(crate) fn lenient(pattern: &[u8]) -> Self {
+        let mut parsed = Parsed::new(pattern);
+        parsed.one_shot = true;
+        Self {
+            parsed: Arc::new(parsed),
+            indexing: Indexing::default(),
+            limits: MatchLimits::new(),
+        }
+    }
+
+    /// Sets how string positions are numbered in the arguments and results of
+    /// this pattern's methods. The default is [`Indexing::OneBased`], like
+    /// Lua.
+    #[must_use]
+    pub fn with_indexing(mut self, indexing: Indexing) -> Self {
+        self.indexing = indexing;
+        self
+    }
+
+    /// Returns how string positions are numbered by this pattern's methods.
+    #[must_use]
+    pub fn indexing(&self) -> Indexing {
+        self.indexing
+    }
+
+    /// Sets the limits on how much work and memory matching this pattern can
+    /// use. The default is [`MatchLimits::new`].
+    #[must_use]
+    pub fn with_limits(mut self, limits: MatchLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the limits on how much work and memory matching this pattern
+    /// can use.
+    #[must_use]
+    pub fn limits(&self) -> &MatchLimits {
+        &self.limits
+    }
+
+    /// The parsed pattern.
+    pub(crate) fn parsed(&self) -> &Parsed {
+        &self.parsed
+    }
+}
@@ -0,0 +1,99 @@
+//! Variants of the Lua string functions that validate the whole pattern
+//! before matching.
+//!
+//! Like Lua, the functions at the crate root only report a syntax error once
+//! the matcher reaches it, so `find(b"a", b"b%", None, false)` returns
+//! `Ok(None)`. The functions in this module take the same arguments but reject
+//! every malformed pattern up front with the same [`Error`](crate::Error)
+//! variants, regardless of the input.
+//!
+//! For piecewise substitution, use [`GSub::from_pattern`](crate::GSub::from_pattern) with a compiled
+//! [`Pattern`].
+
+use crate::{
+    Captures, Match, Pattern, Repl, Result,
+    lua::{FindIter, GMatchIterator},
+};
+
+/// Like [`crate::find`], but rejects a malformed pattern even if the input
+/// never reaches the error.
+///
+/// # Errors
+///
+/// If the pattern string is malformed or too complex to match against the
+/// string, an [`Error`](crate::Error) is returned.
+pub fn find<'a>(
+    s: &'a [u8],
+    pattern: &[u8],
+    init: Option<isize>,
+    plain: bool,
+) -> Result<Option<Match<'a>>> {
+    if plain {
+        crate::find(s, pattern, init, plain)
+    } else {
+        Pattern::new(pattern)?.find(s, init)
+    }
+}
+
+/// Like [`crate::find_iter`], but rejects a malformed pattern before the
+/// first iteration.
+///
+/// # Errors
+///
+/// If the pattern string is malformed, an [`Error`](crate::Error) is returned.
+pub fn find_iter<'a>(s: &'a [u8], pattern: &[u8], init: Option<isize>) -> Result<FindIter<'a>> {
+    Ok(Pattern::new(pattern)?.find_iter(s, init))
+}
+
+/// Like [`crate::is_match`], but rejects a malformed pattern even if the
+/// input never reaches the error.
+///
+/// # Errors
+///
+/// If the pattern string is malformed or too complex to match against the
+/// string, an [`Error`](crate::Error) is returned.
+pub fn is_match(s: &[u8], pattern: &[u8], init: Option<isize>) -> Result<bool> {
+    Pattern::new(pattern)?.is_match(s, init)
+}
+
+/// Like `r#match` at the crate root, but rejects a malformed pattern even if
+/// the input never reaches the error.
+///
+/// # Errors
+///
+/// If the pattern string is malformed or too complex to match against the
+/// string, an [`Error`](crate::Error) is returned.
+pub fn r#match<'a>(
+    text: &'a [u8],
+    pattern: &[u8],
+    init: Option<isize>,
+) -> Result<Option<Captures<'a>>> {
+    Pattern::new(pattern)?.r#match(text, init)
+}
+
+/// Like [`crate::gmatch`], but rejects a malformed pattern before the first
+/// iteration.
+///
+/// # Errors
+///
+/// If the pattern string is malformed, an [`Error`](crate::Error) is returned.
+pub fn gmatch<'a>(s: &'a [u8], pattern: &[u8], init: Option<isize>) -> Result<GMatchIterator<'a>> {
+    Ok(Pattern::new(pattern)?.gmatch(s, init))
+}
+
+/// Like [`crate::gsub`], but rejects a malformed pattern even if the input
+/// never reaches the error.
+///
+/// # Errors
+///
+/// If the pattern string is malformed or too complex to match against the
+/// string, or the replacement string is invalid, an [`Error`](crate::Error) is
+/// returned.
+pub fn gsub<'a>(
+    s: &'a [u8],
+    pattern: &[u8],
+    repl: Repl<'a>,
+    n: Option<usize>,
+) -> Result<(Vec<u8>, usize)> {
+    Pattern::new(pattern)?.gsub(s, repl, n)
+}
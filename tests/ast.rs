@@ -0,0 +1,224 @@
+use lsonar::{
+    Error, Span,
+    ast::{self, Class, ClassKind, Node, NodeKind, Quantifier, Set, SetItem, Single},
+};
+
+#[test]
+fn test_parse_items() {
+    assert_eq!(
+        ast::parse(b"^a.%d[^x-z%s]*$").unwrap().nodes,
+        vec![
+            Node {
+                kind: NodeKind::StartAnchor,
+                span: 0..1,
+            },
+            Node {
+                kind: NodeKind::Single(Single::Literal(b'a')),
+                span: 1..2,
+            },
+            Node {
+                kind: NodeKind::Single(Single::Any),
+                span: 2..3,
+            },
+            Node {
+                kind: NodeKind::Single(Single::Class(Class {
+                    kind: ClassKind::Digit,
+                    negated: false,
+                })),
+                span: 3..5,
+            },
+            Node {
+                kind: NodeKind::Quantified(
+                    Single::Set(Set {
+                        negated: true,
+                        items: vec![
+                            SetItem::Range(b'x', b'z'),
+                            SetItem::Class(Class {
+                                kind: ClassKind::Space,
+                                negated: false,
+                            }),
+                        ],
+                    }),
+                    Quantifier::ZeroOrMore,
+                ),
+                span: 5..14,
+            },
+            Node {
+                kind: NodeKind::EndAnchor,
+                span: 14..15,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_captures() {
+    assert_eq!(
+        ast::parse(b"(a(%w+)())%1").unwrap().nodes,
+        vec![
+            Node {
+                kind: NodeKind::Capture {
+                    index: 1,
+                    nodes: vec![
+                        Node {
+                            kind: NodeKind::Single(Single::Literal(b'a')),
+                            span: 1..2,
+                        },
+                        Node {
+                            kind: NodeKind::Capture {
+                                index: 2,
+                                nodes: vec![Node {
+                                    kind: NodeKind::Quantified(
+                                        Single::Class(Class {
+                                            kind: ClassKind::Alphanumeric,
+                                            negated: false,
+                                        }),
+                                        Quantifier::OneOrMore,
+                                    ),
+                                    span: 3..6,
+                                }],
+                            },
+                            span: 2..7,
+                        },
+                        Node {
+                            kind: NodeKind::PositionCapture { index: 3 },
+                            span: 7..9,
+                        },
+                    ],
+                },
+                span: 0..10,
+            },
+            Node {
+                kind: NodeKind::BackReference { index: 1 },
+                span: 10..12,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_special_items() {
+    assert_eq!(
+        ast::parse(b"%b()*%f[%a]%.%Z-").unwrap().nodes,
+        vec![
+            Node {
+                kind: NodeKind::Balance {
+                    open: b'(',
+                    close: b')',
+                },
+                span: 0..4,
+            },
+            Node {
+                kind: NodeKind::Single(Single::Literal(b'*')),
+                span: 4..5,
+            },
+            Node {
+                kind: NodeKind::Frontier(Set {
+                    negated: false,
+                    items: vec![SetItem::Class(Class {
+                        kind: ClassKind::Letter,
+                        negated: false,
+                    })],
+                }),
+                span: 5..11,
+            },
+            Node {
+                kind: NodeKind::Single(Single::Literal(b'.')),
+                span: 11..13,
+            },
+            Node {
+                kind: NodeKind::Quantified(
+                    Single::Class(Class {
+                        kind: ClassKind::Zero,
+                        negated: true,
+                    }),
+                    Quantifier::Lazy,
+                ),
+                span: 13..16,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(
+        ast::parse(b"[a"),
+        Err(Error::EndsWithoutBracket {
+            span: Span::pattern(0..2)
+        })
+    );
+    assert_eq!(
+        ast::parse(b"a%"),
+        Err(Error::EndsWithPercent {
+            span: Span::pattern(1..2)
+        })
+    );
+    assert_eq!(
+        ast::parse(b"(a"),
+        Err(Error::UnfinishedCapture {
+            span: Span::pattern(0..1)
+        })
+    );
+    assert_eq!(
+        ast::parse(b"a)"),
+        Err(Error::InvalidPatternCapture {
+            span: Span::pattern(1..2)
+        })
+    );
+}
+
+#[test]
+fn test_display() {
+    for (pattern, expected) in [
+        ("^(%w+)%s*=%s*(%w+)$", "^(%w+)%s*=%s*(%w+)$"),
+        ("a.b", "a.b"),
+        ("*", "%*"),
+        ("a$b", "a%$b"),
+        ("^^", "^%^"),
+        ("[]]", "[%]]"),
+        ("[a-]", "[a%-]"),
+        ("[^%a-z]", "[^%a%-z]"),
+        ("[%]-a]", "[%]%-a]"),
+        ("%b()(a)()%1", "%b()(a)()%1"),
+        ("%f[%W]é", "%f[%W]é"),
+    ] {
+        let ast = ast::parse(pattern.as_bytes()).unwrap();
+        assert_eq!(ast.to_string(), expected, "{pattern}");
+        assert_eq!(ast.to_bytes(), expected.as_bytes(), "{pattern}");
+    }
+}
+
+#[test]
+fn test_display_round_trip() {
+    for pattern in [
+        &b"[\0-\x02]+"[..],
+        b"[]%%]",
+        b"[%^%[%-a%]%-b]",
+        b"[--/]",
+        b"[!--]",
+        b"[a-%]]",
+        b"[z-a]",
+        b"[^]]+",
+        b"%f[^\x01-\xff]",
+        b"(%(%w+%))",
+        b"x\0\0abc\0a.",
+    ] {
+        let ast = ast::parse(pattern).unwrap();
+        let printed = ast::parse(&ast.to_bytes()).unwrap();
+        let (set, printed_set) = match (&ast.nodes[0].kind, &printed.nodes[0].kind) {
+            (
+                NodeKind::Single(a) | NodeKind::Quantified(a, _),
+                NodeKind::Single(b) | NodeKind::Quantified(b, _),
+            ) => (a.clone(), b.clone()),
+            (NodeKind::Frontier(a), NodeKind::Frontier(b)) => {
+                (Single::Set(a.clone()), Single::Set(b.clone()))
+            }
+            (NodeKind::Capture { .. }, NodeKind::Capture { .. }) => continue,
+            _ => panic!("mismatched nodes for {pattern:?}"),
+        };
+        for c in 0..=u8::MAX {
+            assert_eq!(set.matches(c), printed_set.matches(c), "{pattern:?} {c}");
+        }
+    }
+}
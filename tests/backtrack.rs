@@ -0,0 +1,178 @@
+mod common;
+
+use common::match_values;
+use lsonar::{Capture, Error, MatchLimits, Pattern, Span, find};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_greedy_gives_back() {
+    assert_eq!(
+        match_values(b"aaab", b"(a*)(a+)b"),
+        Some(vec![b"aa".into(), b"a".into()])
+    );
+    assert_eq!(
+        match_values(b"key = value = x", b"(.*)=(.*)"),
+        Some(vec![b"key = value ".into(), b" x".into()])
+    );
+    assert_eq!(match_values(b"aaa", b"a+a+a+a"), None);
+}
+
+#[test]
+fn test_lazy_takes_more() {
+    assert_eq!(match_values(b"<a><b>", b"<(.-)>"), Some(vec![b"a".into()]));
+    assert_eq!(
+        match_values(b"<a><b>", b"<(.-)>$"),
+        Some(vec![b"a><b".into()])
+    );
+}
+
+#[test]
+fn test_optional_falls_back() {
+    assert_eq!(
+        match_values(b"ab", b"(a?)(a?)b"),
+        Some(vec![b"a".into(), b"".into()])
+    );
+    assert_eq!(match_values(b"b", b"a?a?b"), Some(vec![b"b".into()]));
+}
+
+#[test]
+fn test_captures_are_reverted() {
+    // The first attempt finishes the capture at `b` and fails on `c`, so the
+    // capture must be reopened and finished again later
+    assert_eq!(
+        match_values(b"abxabc", b"(a.-)bc"),
+        Some(vec![b"abxa".into()])
+    );
+    assert_eq!(
+        match_values(b"xy=1;y=2", b"(%a)=()%d;%1"),
+        Some(vec![b"y".into(), Capture::Position(4)])
+    );
+    assert_eq!(
+        match_values(b"aXbXaXa", b"(%a)X(%a)X%1"),
+        Some(vec![b"a".into(), b"b".into()])
+    );
+}
+
+#[test]
+fn test_deep_backtracking() {
+    // More backtracking points than are stored inline
+    let pattern = b"a?".repeat(100);
+    let text = b"a".repeat(100);
+    assert_eq!(
+        find(&text, &pattern, None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(0..100))
+    );
+    let mut pattern = b"(a)".repeat(32);
+    pattern.extend(b"b");
+    let text = b"a".repeat(40);
+    assert_eq!(find(&text, &pattern, None, false), Ok(None));
+}
+
+#[test]
+fn test_memoized_backtracking() {
+    // A balance keeps the pattern on the backtracking matcher, which would
+    // take 2^n steps to backtrack through `a?` repeated n times followed by
+    // `a` repeated n times
+    let mut pattern = b"%b()".to_vec();
+    pattern.extend(b"a?".repeat(40));
+    pattern.extend(b"a".repeat(40));
+    let mut text = b"()".to_vec();
+    text.extend(b"a".repeat(40));
+    assert_eq!(
+        find(&text, &pattern, None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(0..42))
+    );
+    text.pop();
+    assert_eq!(find(&text, &pattern, None, false), Ok(None));
+
+    let text = b"(ab)".repeat(500);
+    assert_eq!(find(&text, b"%b()(.-)(.-)(.-)(.-)x", None, false), Ok(None));
+    assert_eq!(
+        match_values(&text, b"%b()(.*)(.*)%)$"),
+        Some(vec![Capture::Str(&text[4..1999]), b"".into()])
+    );
+}
+
+#[test]
+fn test_memoized_repetitions() {
+    // Each repetition only scans a run once, so these searches take linear
+    // rather than quadratic time. The deadline only stops the test from
+    // hanging if they do not
+    let limits = MatchLimits::new().with_deadline(Instant::now() + Duration::from_secs(30));
+    let text = b"1".repeat(200_000);
+    for pattern in [
+        &b"%d*%d*%f[%a]%b()"[..],
+        b"%d+%d+%f[%a]%b()",
+        b"%d-%d-%f[%a]%b()",
+    ] {
+        let pattern = Pattern::new(pattern).unwrap().with_limits(limits.clone());
+        assert_eq!(pattern.find(&text, None), Ok(None));
+    }
+}
+
+#[test]
+fn test_too_complex() {
+    // A balance keeps the pattern on the backtracking matcher
+    let mut pattern = b"%b()".to_vec();
+    pattern.extend(b"a?".repeat(600));
+    let mut text = b"()".to_vec();
+    text.extend(b"a".repeat(600));
+    assert_eq!(
+        find(&text, &pattern, None, false),
+        Err(Error::TooComplex {
+            span: Span::pattern(1004..1006)
+        })
+    );
+
+    let mut pattern = b"%b()".to_vec();
+    pattern.extend(b"a?".repeat(499));
+    assert_eq!(
+        find(&text, &pattern, None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(0..501))
+    );
+}
+
+#[test]
+fn test_max_depth() {
+    let mut pattern = b"%b()".to_vec();
+    pattern.extend(b"a?".repeat(100));
+    let pattern = Pattern::new(&pattern).unwrap();
+    let mut text = b"()".to_vec();
+    text.extend(b"a".repeat(100));
+    assert_eq!(
+        pattern
+            .clone()
+            .with_limits(MatchLimits::new().with_max_depth(60))
+            .find(&text, None),
+        Err(Error::TooComplex {
+            span: Span::pattern(124..126)
+        })
+    );
+    assert_eq!(
+        pattern
+            .with_limits(MatchLimits::new().with_max_depth(101))
+            .find(&text, None)
+            .map(|m| m.map(|m| m.range())),
+        Ok(Some(0..102))
+    );
+}
+
+#[test]
+fn test_small_stack() {
+    // Backtracking points far beyond the default limit are kept on the heap,
+    // not on the native stack of the thread
+    let mut pattern = b"%b()".to_vec();
+    pattern.extend(b"a?".repeat(5000));
+    pattern.extend(b"b");
+    let pattern = Pattern::new(&pattern)
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_depth(10_000));
+    let mut text = b"()".to_vec();
+    text.extend(b"a".repeat(5000));
+    text.extend(b"b");
+    let handle = std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(move || pattern.find(&text, None).map(|m| m.map(|m| m.range())))
+        .unwrap();
+    assert_eq!(handle.join().unwrap(), Ok(Some(0..5003)));
+}
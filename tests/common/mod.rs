@@ -0,0 +1,20 @@
+//! Helpers shared by the integration tests.
+
+// Each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use lsonar::{Capture, find, r#match};
+
+/// Returns the 1-based start and end of the first match of `pattern` in `s`.
+pub fn find_range(s: &[u8], pattern: &[u8], init: Option<isize>) -> Option<(usize, usize)> {
+    find(s, pattern, init, false)
+        .unwrap()
+        .map(|m| (m.start, m.end))
+}
+
+/// Returns the captures of the first match of `pattern` in `s`.
+pub fn match_values<'a>(s: &'a [u8], pattern: &[u8]) -> Option<Vec<Capture<'a>>> {
+    r#match(s, pattern, None)
+        .unwrap()
+        .map(|captures| captures.values().to_vec())
+}
@@ -0,0 +1,96 @@
+use lsonar::{Complexity, ComplexityClass, Error, Span, analyze_complexity};
+
+fn class(pattern: &[u8]) -> ComplexityClass {
+    analyze_complexity(pattern).unwrap().class
+}
+
+#[test]
+fn test_linear() {
+    assert_eq!(
+        analyze_complexity(b"hello"),
+        Ok(Complexity {
+            class: ComplexityClass::Linear,
+            items: vec![]
+        })
+    );
+    assert_eq!(class(b"^%a+=%d"), ComplexityClass::Linear);
+    // The first attempt always succeeds
+    assert_eq!(class(b"a*a*a*"), ComplexityClass::Linear);
+    assert_eq!(class(b"(%d*)()"), ComplexityClass::Linear);
+}
+
+#[test]
+fn test_polynomial() {
+    assert_eq!(
+        analyze_complexity(b"%d*%d*%d*$"),
+        Ok(Complexity {
+            class: ComplexityClass::Polynomial(4),
+            items: vec![
+                Span::pattern(0..3),
+                Span::pattern(3..6),
+                Span::pattern(6..9)
+            ]
+        })
+    );
+    assert_eq!(
+        analyze_complexity(b".-.-.-x"),
+        Ok(Complexity {
+            class: ComplexityClass::Polynomial(4),
+            items: vec![
+                Span::pattern(0..2),
+                Span::pattern(2..4),
+                Span::pattern(4..6)
+            ]
+        })
+    );
+    // Anchored patterns are only tried at one start position
+    assert_eq!(class(b"^(%d*)(%d*)(%d*)$"), ComplexityClass::Polynomial(3));
+    assert_eq!(class(b"%d+x"), ComplexityClass::Polynomial(2));
+    // Items which the other quantifiers cannot match split the runs
+    assert_eq!(class(b"%d*x%d*x%d*y"), ComplexityClass::Polynomial(2));
+    assert_eq!(class(b".-x.-x.-y"), ComplexityClass::Polynomial(4));
+    // Back-references and balances do work for each character
+    assert_eq!(class(b"^(.-)%1$"), ComplexityClass::Polynomial(2));
+    assert_eq!(class(b"%b()"), ComplexityClass::Polynomial(2));
+    // A frontier never matches inside a run
+    assert_eq!(
+        analyze_complexity(b"%d*%d*%d*%f[%a]"),
+        Ok(Complexity {
+            class: ComplexityClass::Polynomial(4),
+            items: vec![
+                Span::pattern(0..3),
+                Span::pattern(3..6),
+                Span::pattern(6..9)
+            ]
+        })
+    );
+    assert_eq!(class(b"%d*%d*%f[%a]"), ComplexityClass::Polynomial(3));
+    assert_eq!(class(b"^%d*%d*%f[%d]"), ComplexityClass::Polynomial(2));
+}
+
+#[test]
+fn test_exponential() {
+    let mut pattern = b"a?".repeat(10);
+    pattern.extend(b"b");
+    let complexity = analyze_complexity(&pattern).unwrap();
+    assert_eq!(complexity.class, ComplexityClass::Exponential);
+    assert_eq!(complexity.items.len(), 10);
+    assert_eq!(class(b"a?b?c?d?e?f?g?h?i"), ComplexityClass::Linear);
+}
+
+#[test]
+fn test_ordered() {
+    assert!(ComplexityClass::Linear < ComplexityClass::Polynomial(2));
+    assert!(ComplexityClass::Polynomial(2) < ComplexityClass::Polynomial(3));
+    assert!(ComplexityClass::Polynomial(9) < ComplexityClass::Exponential);
+}
+
+#[test]
+fn test_malformed() {
+    assert_eq!(
+        analyze_complexity(b"a%"),
+        Err(Error::EndsWithPercent {
+            span: Span::pattern(1..2)
+        })
+    );
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::find_range;
+use lsonar::{Capture, Pattern, Repl, gsub, is_match, r#match};
+
+#[test]
+fn test_leftmost_start() {
+    assert_eq!(find_range(b"xxaaab", b"a*b", None), Some((3, 6)));
+    assert_eq!(find_range(b"abcabd", b"%a-d", None), Some((1, 6)));
+    assert_eq!(find_range(b"aaa", b"a-", None), Some((1, 0)));
+    assert_eq!(find_range(b"foo  bar", b"%s+%a+$", None), Some((4, 8)));
+    assert_eq!(find_range(b"foo  bar", b"^%a+%s*", None), Some((1, 5)));
+    assert_eq!(find_range(b"foo  bar", b"^%s+", None), None);
+    assert_eq!(find_range(b"a.b.c", b"[^.]+$", Some(3)), Some((5, 5)));
+}
+
+#[test]
+fn test_frontiers() {
+    assert_eq!(
+        find_range(b"THE (quick) fox", b"%f[%a]%a+%f[%A]", Some(2)),
+        Some((6, 10))
+    );
+    assert_eq!(find_range(b"aaa bbb", b"%f[%w]%w+$", None), Some((5, 7)));
+    assert_eq!(find_range(b"abc", b"%f[%l]b", None), None);
+    // The byte before the start of the search still counts
+    assert_eq!(find_range(b"abc", b"%f[%l]%l+", Some(2)), None);
+    assert_eq!(find_range(b"a\0b", b"%f[%z]%z", None), Some((2, 2)));
+    assert_eq!(find_range(b"\0a", b"%f[%Z]a*", None), Some((2, 2)));
+    assert_eq!(
+        gsub(b"one two  three", b"%f[%w]%w+", Repl::String(b"<%0>"), None).unwrap(),
+        (b"<one> <two>  <three>".to_vec(), 3)
+    );
+}
+
+#[test]
+fn test_captures_within_match() {
+    assert_eq!(
+        r#match(b"k1 = v1; k2 = v2", b"(%w+) = ([^;]-);", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [b"k1".into(), b"v1".into()]
+    );
+    assert_eq!(
+        r#match(b"  x", b"()%s*()x", Some(2))
+            .unwrap()
+            .unwrap()
+            .values(),
+        [Capture::Position(2), Capture::Position(3)]
+    );
+    assert_eq!(
+        r#match(b"2024-01-31", b"(%d+)-(%d%d)-(%d%d)", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [b"2024".into(), b"01".into(), b"31".into()]
+    );
+}
+
+#[test]
+fn test_is_match() {
+    assert_eq!(is_match(b"error: x", b"^%a+: %a$", None), Ok(true));
+    assert_eq!(is_match(b"error: xy", b"^%a+: %a$", None), Ok(false));
+    assert_eq!(is_match(b"ab ab", b"%f[%a]b", None), Ok(false));
+    assert_eq!(is_match(b"ab ab", b"a%f[%A]", Some(3)), Ok(false));
+}
+
+#[test]
+fn test_many_states() {
+    // Each position of the `a` in the last 12 bytes needs its own state, so
+    // the cache fills up and the search falls back to another engine
+    let pattern = Pattern::new(b"a............$").unwrap();
+    let mut text = Vec::new();
+    let mut seed = 1u32;
+    for _ in 0..20000 {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        text.push(if seed >> 16 & 1 == 0 { b'a' } else { b'b' });
+    }
+    let expected = text.len() - 13;
+    text[expected] = b'a';
+    assert_eq!(
+        pattern.find(&text, None).unwrap().map(|m| m.start),
+        Some(expected + 1)
+    );
+    assert_eq!(pattern.is_match(&text, None), Ok(true));
+    assert_eq!(
+        pattern.is_match(&text[..text.len() - 1], Some(-12)),
+        Ok(false)
+    );
+}
+
+#[test]
+fn test_shared_between_threads() {
+    let pattern = Pattern::new(b"%f[%w](%w+)%s*=%s*(%d+)").unwrap();
+    let text = b"x = y, count = 42".repeat(100);
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    let captures = pattern.r#match(&text, None).unwrap().unwrap();
+                    assert_eq!(captures.values(), [b"count".into(), b"42".into()]);
+                }
+            });
+        }
+    });
+}
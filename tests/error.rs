@@ -0,0 +1,121 @@
+use lsonar::{Error, Repl, Source, Span, find, gsub, validate};
+
+#[test]
+fn test_anchored_pattern_spans() {
+    assert_eq!(
+        find(b"b", b"^b%", None, false),
+        Err(Error::EndsWithPercent {
+            span: Span::pattern(2..3)
+        })
+    );
+    assert_eq!(
+        find(b"ab", b"^a(b", None, false),
+        Err(Error::UnfinishedCapture {
+            span: Span::pattern(2..3)
+        })
+    );
+    assert_eq!(
+        find(b"a", b"^[a", None, false),
+        Err(Error::EndsWithoutBracket {
+            span: Span::pattern(1..3)
+        })
+    );
+}
+
+#[test]
+fn test_replacement_spans() {
+    let error = gsub(b"alo", b"(a)", Repl::String(b"<%1%2>"), None).unwrap_err();
+    assert_eq!(
+        error,
+        Error::InvalidCaptureIndex {
+            span: Span::replacement(3..5),
+            index: 2
+        }
+    );
+    assert_eq!(error.span().source, Source::Replacement);
+
+    assert_eq!(
+        gsub(b"alo", b".", Repl::String(b"x%y"), None),
+        Err(Error::InvalidReplacement {
+            span: Span::replacement(1..3)
+        })
+    );
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(
+        find(b"a", b"a%", None, false).unwrap_err().to_string(),
+        "malformed pattern (ends with '%') at pattern byte 1"
+    );
+    assert_eq!(
+        find(b"a", b"[a", None, false).unwrap_err().to_string(),
+        "malformed pattern (missing ']') at pattern bytes 0..2"
+    );
+}
+
+#[test]
+fn test_render() {
+    let pattern = b"(%w+)=%";
+    let error = find(b"key=", pattern, None, false).unwrap_err();
+    assert_eq!(
+        error.render(pattern, b""),
+        [
+            "malformed pattern (ends with '%') at pattern byte 6",
+            "  pattern: (%w+)=%",
+            "                 ^ nothing follows '%'",
+        ]
+        .join("\n")
+    );
+
+    let pattern = b"%bx";
+    let error = validate(pattern).unwrap_err();
+    assert_eq!(
+        error.render(pattern, b""),
+        [
+            "malformed pattern (missing arguments to '%b') at pattern bytes 0..3",
+            "  pattern: %bx",
+            "           ^^^ expected two characters after '%b'",
+        ]
+        .join("\n")
+    );
+
+    let replacement = b"[%1] %3";
+    let error = gsub(b"a b", b"(%a) (%a)", Repl::String(replacement), None).unwrap_err();
+    assert_eq!(
+        error.render(b"(%a) (%a)", replacement),
+        [
+            "invalid capture index %3 at replacement bytes 5..7",
+            "  replacement: [%1] %3",
+            "                    ^^ no capture 3",
+        ]
+        .join("\n")
+    );
+}
+
+#[test]
+fn test_render_unprintable() {
+    let pattern = "é\tÿ(".as_bytes();
+    let error = validate(pattern).unwrap_err();
+    assert_eq!(
+        error.render(pattern, b""),
+        [
+            "unfinished capture at pattern byte 5",
+            "  pattern: é\\tÿ(",
+            "               ^ capture is never closed",
+        ]
+        .join("\n")
+    );
+
+    let pattern = b"\xff\x01%1";
+    let error = validate(pattern).unwrap_err();
+    assert_eq!(
+        error.render(pattern, b""),
+        [
+            "invalid capture index %1 at pattern bytes 2..4",
+            "  pattern: \\xff\\u{1}%1",
+            "                    ^^ no finished capture 1",
+        ]
+        .join("\n")
+    );
+}
@@ -0,0 +1,105 @@
+use lsonar::{Capture, Indexing, Pattern, Result, find_iter, gmatch};
+
+/// Collects the start, end, and captures of every match, like calling Lua
+/// `string.find` in a loop.
+fn collect_find_iter<'a>(
+    s: &'a [u8],
+    pattern: &[u8],
+    init: Option<isize>,
+) -> Result<Vec<(usize, usize, Vec<Capture<'a>>)>> {
+    find_iter(s, pattern, init)?
+        .map(|m| m.map(|m| (m.start, m.end, m.captures.iter().skip(1).collect())))
+        .collect()
+}
+
+#[test]
+fn test_positions() {
+    assert_eq!(
+        collect_find_iter(b"hello hello", b"hello", None),
+        Ok(vec![(1, 5, vec![]), (7, 11, vec![])])
+    );
+    assert_eq!(
+        collect_find_iter(b"abc123def456", b"%d+", None),
+        Ok(vec![(4, 6, vec![]), (10, 12, vec![])])
+    );
+    assert_eq!(collect_find_iter(b"hello", b"x", None), Ok(vec![]));
+}
+
+#[test]
+fn test_captures() {
+    assert_eq!(
+        collect_find_iter(b"a=1 b=2", b"(%a)=()(%d)", None),
+        Ok(vec![
+            (1, 3, vec![b"a".into(), Capture::Position(3), b"1".into()]),
+            (5, 7, vec![b"b".into(), Capture::Position(7), b"2".into()])
+        ])
+    );
+}
+
+#[test]
+fn test_empty_matches() {
+    assert_eq!(
+        collect_find_iter(b"abc", b"", None),
+        Ok(vec![
+            (1, 0, vec![]),
+            (2, 1, vec![]),
+            (3, 2, vec![]),
+            (4, 3, vec![])
+        ])
+    );
+    // An empty match right after a non-empty one is still reported, like
+    // `gmatch`
+    assert_eq!(
+        collect_find_iter(b"ab1", b"%a*", None),
+        Ok(vec![(1, 2, vec![]), (3, 2, vec![]), (4, 3, vec![])])
+    );
+}
+
+#[test]
+fn test_init() {
+    assert_eq!(
+        collect_find_iter(b"one two three", b"%a+", Some(5)),
+        Ok(vec![(5, 7, vec![]), (9, 13, vec![])])
+    );
+    assert_eq!(
+        collect_find_iter(b"one two three", b"%a+", Some(-5)),
+        Ok(vec![(9, 13, vec![])])
+    );
+    assert_eq!(collect_find_iter(b"abc", b"", Some(10)), Ok(vec![]));
+}
+
+#[test]
+fn test_same_matches_as_gmatch() {
+    let text = b"  key = value; other=1;;  ";
+    for pattern in [&b"%w*"[..], b"(%w+)%s*=%s*(%w+)", b";*", b"()", b"%s*$"] {
+        let found = find_iter(text, pattern, None)
+            .unwrap()
+            .map(|m| m.unwrap().captures.values().to_vec())
+            .collect::<Vec<_>>();
+        let matched = gmatch(text, pattern, None)
+            .unwrap()
+            .map(|captures| captures.unwrap().values().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(found, matched, "{}", pattern.escape_ascii());
+    }
+}
+
+#[test]
+fn test_zero_based() {
+    let pattern = Pattern::new(b"l+")
+        .unwrap()
+        .with_indexing(Indexing::ZeroBased);
+    assert_eq!(
+        pattern
+            .find_iter(b"hello world", Some(3))
+            .map(|m| m.map(|m| (m.start, m.end)))
+            .collect::<Result<Vec<_>>>(),
+        Ok(vec![(3, 4), (9, 10)])
+    );
+}
+
+#[test]
+fn test_error() {
+    let mut it = find_iter(b"ab", b"a(", None).unwrap();
+    assert!(it.next().unwrap().is_err());
+}
@@ -0,0 +1,149 @@
+use lsonar::{Error, Indexing, Match, MatchLimits, Pattern, Progress, Result};
+
+/// Runs a search with `fuel` steps at a time until it finishes, and returns
+/// its result together with the number of times it was suspended.
+fn find_in_slices<'a>(
+    pattern: &Pattern,
+    s: &'a [u8],
+    init: Option<isize>,
+    fuel: u64,
+) -> (Result<Option<Match<'a>>>, usize) {
+    let mut suspensions = 0;
+    let mut progress = pattern.find_with_fuel(s, init, fuel);
+    loop {
+        match progress {
+            Ok(Progress::Done(found)) => return (Ok(found), suspensions),
+            Ok(Progress::Suspended(suspended)) => {
+                suspensions += 1;
+                progress = suspended.resume(fuel);
+            }
+            Err(error) => return (Err(error), suspensions),
+        }
+    }
+}
+
+#[test]
+fn test_same_as_find() {
+    let cases: &[(&[u8], &[u8], Option<isize>)] = &[
+        (b"(%a+)=(%d+)", b"x = 1, key=42, y=3", None),
+        (b"(.-)%1%d", b"abcabcabc1", None),
+        (b"^(a*)(a+)b", b"aaaaaaaaaab", None),
+        (b"<(.-)>$", b"<a><b><c>", None),
+        (b"()%f[%w]%w+()", b"  hello world", Some(4)),
+        (b"%b()x?", b"((a)(b))x", None),
+        (b"(a?)(a?)(a?)b", b"aaaaaab", None),
+        (b"(%d+)%s*$", b"12 34  ", None),
+        (b"a-b", b"aaaaaaaa", None),
+        (b"hello", b"say hello", None),
+        (b"x", b"aaaa", None),
+    ];
+    for &(pattern, s, init) in cases {
+        let pattern = Pattern::new(pattern).unwrap();
+        let expected = pattern.find(s, init);
+        for fuel in [1, 2, 5, 1000] {
+            let (found, _) = find_in_slices(&pattern, s, init, fuel);
+            assert_eq!(found, expected, "pattern {pattern:?}, fuel {fuel}");
+        }
+        let pattern = pattern.with_indexing(Indexing::ZeroBased);
+        assert_eq!(
+            find_in_slices(&pattern, s, init, 3).0,
+            pattern.find(s, init)
+        );
+    }
+}
+
+#[test]
+fn test_suspends() {
+    let pattern = Pattern::new(b"(.-)%1%d").unwrap();
+    let s = b"a".repeat(100);
+    let (found, suspensions) = find_in_slices(&pattern, &s, None, 100);
+    assert_eq!(found, Ok(None));
+    assert!(suspensions > 10);
+
+    let s = [b"ab".repeat(50).as_slice(), b"1"].concat();
+    let (found, suspensions) = find_in_slices(&pattern, &s, None, 100);
+    assert_eq!(found, pattern.find(&s, None));
+    assert!(suspensions > 0);
+
+    // A search with enough fuel never suspends
+    let (_, suspensions) = find_in_slices(&pattern, &s, None, u64::MAX);
+    assert_eq!(suspensions, 0);
+}
+
+#[test]
+fn test_errors() {
+    let mut pattern = b"%b()".to_vec();
+    pattern.extend(b"a?".repeat(100));
+    let pattern = Pattern::new(&pattern)
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_depth(60));
+    let mut s = b"()".to_vec();
+    s.extend(b"a".repeat(100));
+    let (found, _) = find_in_slices(&pattern, &s, None, 10);
+    assert_eq!(found, pattern.find(&s, None));
+    assert!(found.is_err());
+
+    // Like `find`, which matches regular patterns with automata, the depth
+    // limit does not apply to them
+    let deep = [b"a-".repeat(600), b"b".to_vec()].concat();
+    let cases: &[(&[u8], usize, &[u8])] = &[
+        (&deep, 500, b"aaaaaaaaaab"),
+        (b"a?a?a?a?a?a?b", 3, b"aaaaaab"),
+        (b"^(a-)b", 2, b"aaab"),
+    ];
+    for &(pattern, max_depth, s) in cases {
+        let pattern = Pattern::new(pattern)
+            .unwrap()
+            .with_limits(MatchLimits::new().with_max_depth(max_depth));
+        let (found, _) = find_in_slices(&pattern, s, None, 10);
+        assert_eq!(found, pattern.find(s, None));
+        assert!(found.unwrap().is_some());
+    }
+}
+
+#[test]
+fn test_no_fuel() {
+    // Every call takes at least one step
+    let pattern = Pattern::new(b"(.-)%1%d").unwrap();
+    let s = b"abab1";
+    let (found, suspensions) = find_in_slices(&pattern, s, None, 0);
+    assert_eq!(found, pattern.find(s, None));
+    assert!(suspensions > 0);
+}
+
+#[test]
+fn test_max_steps() {
+    // The step limit applies to the whole search, however it is sliced
+    let pattern = Pattern::new(b"(.-)%1%d").unwrap();
+    let s = [b"ab".repeat(50).as_slice(), b"1"].concat();
+    let (found, suspensions) = find_in_slices(&pattern, &s, None, u64::MAX);
+    assert_eq!(suspensions, 0);
+    assert!(found.unwrap().is_some());
+
+    let mut steps = 1;
+    let limited = loop {
+        let limited = pattern
+            .clone()
+            .with_limits(MatchLimits::new().with_max_steps(steps));
+        if find_in_slices(&limited, &s, None, u64::MAX).0.is_ok() {
+            break limited;
+        }
+        steps *= 2;
+    };
+    for fuel in [1, 7, 100] {
+        assert!(
+            find_in_slices(&limited, &s, None, fuel)
+                .0
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    let limited = pattern.with_limits(MatchLimits::new().with_max_steps(steps / 4));
+    for fuel in [1, 7, 100, u64::MAX] {
+        assert!(matches!(
+            find_in_slices(&limited, &s, None, fuel).0,
+            Err(Error::StepLimitExceeded { .. })
+        ));
+    }
+}
@@ -1,12 +1,9 @@
-use lsonar::{Result, gmatch};
-use std::borrow::Cow;
+use lsonar::{Capture, Result, gmatch};
 
-fn collect_gmatch_results<'a>(
-    text: &'a [u8],
-    pattern: &'a [u8],
-) -> Result<Vec<Vec<Cow<'a, [u8]>>>> {
+fn collect_gmatch_results<'a>(text: &'a [u8], pattern: &'a [u8]) -> Result<Vec<Vec<Capture<'a>>>> {
     let it = gmatch(text, pattern, None)?;
-    it.collect::<Result<_, _>>()
+    it.map(|captures| captures.map(|captures| captures.values().to_vec()))
+        .collect::<Result<_, _>>()
 }
 
 #[test]
@@ -60,7 +57,7 @@ fn test_single_char_captures() {
 fn test_empty_captures() {
     assert_eq!(
         collect_gmatch_results(b"abc", b"()a()"),
-        Ok(vec![vec![b"1".into(), b"2".into()]])
+        Ok(vec![vec![Capture::Position(1), Capture::Position(2)]])
     );
 }
 
@@ -70,7 +67,7 @@ fn test_empty_pattern() {
     assert_eq!(result.len(), 4);
 
     for r in result {
-        assert_eq!(r, vec![Cow::Borrowed(b"")]);
+        assert_eq!(r, vec![Capture::Str(b"")]);
     }
 }
 
@@ -127,6 +124,6 @@ fn test_dot_pattern() {
     assert_eq!(result.len(), 11);
 
     for (i, v) in result.into_iter().enumerate() {
-        assert_eq!(v[0], vec![b"hello world"[i]]);
+        assert_eq!(v[0], Capture::Str(&b"hello world"[i..=i]));
     }
 }
@@ -89,7 +89,11 @@ fn test_function_replacement() {
         gsub(
             b"hello world",
             b"%w+",
-            Repl::Function(&mut |captures| { captures.first().map(|s| s.to_ascii_uppercase()) }),
+            Repl::Function(&mut |captures| {
+                captures
+                    .first()
+                    .map(|s| s.to_lua_bytes().to_ascii_uppercase())
+            }),
             None
         ),
         Ok((b"HELLO WORLD".to_vec(), 2))
@@ -106,8 +110,8 @@ fn test_function_with_captures() {
                 Some(
                     format!(
                         "{}={}",
-                        str::from_utf8(captures[0].as_ref()).unwrap(),
-                        str::from_utf8(captures[1].as_ref())
+                        str::from_utf8(captures[0].to_lua_bytes().as_ref()).unwrap(),
+                        str::from_utf8(captures[1].to_lua_bytes().as_ref())
                             .unwrap()
                             .parse::<i32>()
                             .unwrap()
@@ -133,7 +137,7 @@ fn test_table_replacement() {
         gsub(
             b"hello world",
             b"%w+",
-            Repl::Table(&|key| table.get(key.as_ref()).map(|v| v.to_vec())),
+            Repl::Table(&|key| table.get(key.to_lua_bytes().as_ref()).map(|v| v.to_vec())),
             None
         ),
         Ok(("привет мир".as_bytes().to_vec(), 2))
@@ -149,7 +153,7 @@ fn test_partial_table_replacement() {
         gsub(
             b"hello world",
             b"%w+",
-            Repl::Table(&|key| table.get(key.as_ref()).map(|v| v.to_vec())),
+            Repl::Table(&|key| table.get(key.to_lua_bytes().as_ref()).map(|v| v.to_vec())),
             None
         ),
         Ok(("привет world".as_bytes().to_vec(), 2))
@@ -166,7 +170,7 @@ fn test_table_with_captures() {
         gsub(
             b"name=John age=25",
             b"(%w+)=%w+",
-            Repl::Table(&|key| table.get(key.as_ref()).map(|v| v.to_vec())),
+            Repl::Table(&|key| table.get(key.to_lua_bytes().as_ref()).map(|v| v.to_vec())),
             None
         ),
         Ok(("имя возраст".as_bytes().to_vec(), 2))
@@ -0,0 +1,79 @@
+use lsonar::{Capture, Indexing, Pattern, Repl, Result, find};
+
+#[test]
+fn test_default_is_one_based() {
+    let pattern = Pattern::new(b"l+").unwrap();
+    assert_eq!(pattern.indexing(), Indexing::OneBased);
+
+    let m = pattern.find(b"hello", None).unwrap().unwrap();
+    assert_eq!((m.start, m.end), (3, 4));
+    assert_eq!(m.range(), 2..4);
+
+    let m = find(b"hello", b"l", Some(4), true).unwrap().unwrap();
+    assert_eq!((m.start, m.end), (4, 4));
+    assert_eq!(m.range(), 3..4);
+}
+
+#[test]
+fn test_zero_based() {
+    let pattern = Pattern::new(b"()l+()")
+        .unwrap()
+        .with_indexing(Indexing::ZeroBased);
+    assert_eq!(pattern.indexing(), Indexing::ZeroBased);
+
+    let m = pattern.find(b"hello", None).unwrap().unwrap();
+    assert_eq!((m.start, m.end), (2, 4));
+    assert_eq!(m.range(), 2..4);
+    assert_eq!(
+        m.captures.values(),
+        [Capture::Position(2), Capture::Position(4)]
+    );
+
+    // `init` is 0-based too, so 3 starts at the second `l`
+    let m = pattern.find(b"hello", Some(3)).unwrap().unwrap();
+    assert_eq!(m.range(), 3..4);
+    assert_eq!(pattern.find(b"hello", Some(-2)).unwrap().unwrap().start, 3);
+
+    assert_eq!(
+        pattern
+            .r#match(b"hello", Some(1))
+            .unwrap()
+            .unwrap()
+            .values(),
+        [Capture::Position(2), Capture::Position(4)]
+    );
+    assert_eq!(
+        pattern
+            .gmatch(b"all lol", Some(0))
+            .map(|captures| captures.map(|captures| captures.values().to_vec()))
+            .collect::<Result<Vec<_>>>(),
+        Ok(vec![
+            vec![Capture::Position(1), Capture::Position(3)],
+            vec![Capture::Position(4), Capture::Position(5)],
+            vec![Capture::Position(6), Capture::Position(7)]
+        ])
+    );
+    assert_eq!(
+        pattern.gsub(b"all", Repl::String(b"[%1,%2]"), None),
+        Ok((b"a[1,3]".to_vec(), 1))
+    );
+}
+
+#[test]
+fn test_empty_match_range() {
+    for indexing in [Indexing::OneBased, Indexing::ZeroBased] {
+        let pattern = Pattern::new(b"x*").unwrap().with_indexing(indexing);
+        let m = pattern.find(b"abc", None).unwrap().unwrap();
+        assert_eq!(m.range(), 0..0, "{indexing:?}");
+    }
+}
+
+#[test]
+fn test_free_functions_are_unaffected() {
+    let _zero_based = Pattern::new(b"l")
+        .unwrap()
+        .with_indexing(Indexing::ZeroBased);
+    let m = find(b"hello", b"()l", None, false).unwrap().unwrap();
+    assert_eq!((m.start, m.end), (3, 3));
+    assert_eq!(m.captures[1], Capture::Position(3));
+}
@@ -0,0 +1,132 @@
+use lsonar::{Pattern, is_match};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// Counts the allocations made by the current thread.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn test_is_match() {
+    assert_eq!(is_match(b"hello world", b"o w", None), Ok(true));
+    assert_eq!(is_match(b"hello world", b"^world", None), Ok(false));
+    assert_eq!(is_match(b"hello world", b"(o)()%s*(%a+)$", None), Ok(true));
+    assert_eq!(is_match(b"", b"", None), Ok(true));
+    assert_eq!(is_match(b"", b"x*", None), Ok(true));
+    assert_eq!(is_match(b"", b"x", None), Ok(false));
+}
+
+#[test]
+fn test_init() {
+    assert_eq!(is_match(b"hello", b"h", Some(1)), Ok(true));
+    assert_eq!(is_match(b"hello", b"h", Some(2)), Ok(false));
+    assert_eq!(is_match(b"hello", b"lo", Some(-2)), Ok(true));
+    assert_eq!(is_match(b"hello", b"", Some(10)), Ok(false));
+}
+
+#[test]
+fn test_same_as_find() {
+    let text = b"Content-Type: text/html; charset=utf-8";
+    for pattern in [
+        &b"^Content%-Type:"[..],
+        b"charset=(%S+)$",
+        b"%f[%w]html%f[%W]",
+        b"%b()",
+        b"(t)e%1",
+        b"^%s",
+        // Without special characters, the pattern is a plain string
+        b"/html;",
+        b")",
+    ] {
+        assert_eq!(
+            is_match(text, pattern, None),
+            lsonar::find(text, pattern, None, false).map(|m| m.is_some()),
+            "{}",
+            pattern.escape_ascii()
+        );
+    }
+}
+
+#[test]
+fn test_errors() {
+    assert!(is_match(b"ab", b"a(", None).is_err());
+    assert!(is_match(b"ab", b"a%", None).is_err());
+    // Like Lua, an error the matcher never reaches is not reported
+    assert_eq!(is_match(b"ab", b"x%", None), Ok(false));
+    // Like `find`, a pattern without special characters is never parsed
+    assert_eq!(is_match(b"bb)x", b")", None), Ok(true));
+    assert_eq!(is_match(b"bb)x", b"a)", None), Ok(false));
+}
+
+#[test]
+fn test_does_not_allocate() {
+    let pattern = Pattern::new(b"()(%w+)=(%d+)()").unwrap();
+    // The first matches cache the states of the automaton
+    assert_eq!(pattern.is_match(b"key value=10", None), Ok(true));
+    assert_eq!(pattern.is_match(b"key value", None), Ok(false));
+    let before = ALLOCATIONS.with(Cell::get);
+    assert_eq!(pattern.is_match(b"key value=10", None), Ok(true));
+    assert_eq!(pattern.is_match(b"key value", None), Ok(false));
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+}
+
+#[test]
+fn test_free_function_does_not_allocate() {
+    let text = b"Content-Type: text/html; charset=utf-8";
+    for pattern in [
+        &b"^Content%-Type:%s*(%w+)"[..],
+        b"charset=([%w%-]+)",
+        b"%f[%w]html%f[%W]",
+        b"(t)ex%1",
+        b"[^%s;]+$",
+        b"text/html",
+    ] {
+        let before = ALLOCATIONS.with(Cell::get);
+        assert_eq!(is_match(text, pattern, None), Ok(true));
+        assert_eq!(
+            ALLOCATIONS.with(Cell::get),
+            before,
+            "{}",
+            pattern.escape_ascii()
+        );
+    }
+}
+
+#[test]
+fn test_deep_or_long_matches() {
+    // Patterns which backtrack too deep or too long to match without
+    // allocating are still matched
+    let deep = [&b"a?"[..]; 100].concat();
+    let text = [b'a'; 100];
+    let digits = [b'1'; 100];
+    for (text, pattern) in [
+        (&text[..], &[&deep[..], &text[..]].concat()[..]),
+        (&text[..], &[&deep[..], b"b"].concat()[..]),
+        (&digits[..], b"%d*%d*%d*x"),
+        (&digits[..], b"(%d-)%1x"),
+    ] {
+        assert_eq!(
+            is_match(text, pattern, None),
+            lsonar::find(text, pattern, None, false).map(|m| m.is_some())
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use lsonar::{Error, MatchLimits, Pattern, Repl, Span};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn test_defaults() {
+    let limits = MatchLimits::default();
+    assert_eq!(limits, MatchLimits::new());
+    assert_eq!(limits.max_depth(), 500);
+    assert_eq!(limits.max_steps(), None);
+    assert_eq!(limits.max_captures(), lsonar::LUA_MAXCAPTURES);
+    assert_eq!(Pattern::new(b"a").unwrap().limits(), &limits);
+    assert_eq!(
+        MatchLimits::new().with_max_captures(100).max_captures(),
+        lsonar::LUA_MAXCAPTURES
+    );
+}
+
+#[test]
+fn test_max_steps_backtracking() {
+    // The back-reference keeps the pattern on the backtracking matcher
+    let s = b"a".repeat(200);
+    let pattern = Pattern::new(b"(.-)%1%d").unwrap();
+    assert_eq!(pattern.find(&s, None), Ok(None));
+
+    let pattern = pattern.with_limits(MatchLimits::new().with_max_steps(1000));
+    assert!(matches!(
+        pattern.find(&s, None),
+        Err(Error::StepLimitExceeded { .. })
+    ));
+    assert!(matches!(
+        pattern.is_match(&s, None),
+        Err(Error::StepLimitExceeded { .. })
+    ));
+    // A short input fits in the budget
+    assert!(pattern.find(b"aa1", None).unwrap().is_some());
+}
+
+#[test]
+fn test_max_steps_automata() {
+    let s = b"1".repeat(1000);
+    let pattern = Pattern::new(b"%d+%a")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_steps(100));
+    assert_eq!(
+        pattern.find(&s, None),
+        Err(Error::StepLimitExceeded {
+            span: Span::pattern(0..5)
+        })
+    );
+    assert_eq!(
+        pattern.is_match(&s, None),
+        Err(Error::StepLimitExceeded {
+            span: Span::pattern(0..5)
+        })
+    );
+    assert!(pattern.find(b"12a", None).unwrap().is_some());
+
+    let pattern = Pattern::new(b"a?c")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_steps(0));
+    assert!(matches!(
+        pattern.find(b"c", None),
+        Err(Error::StepLimitExceeded { .. })
+    ));
+}
+
+#[test]
+fn test_max_steps_whole_call() {
+    // The searches of a substitution share one budget
+    let s = b"1 2 3 4 5 6 7 8 9 ".repeat(10);
+    let pattern = Pattern::new(b"%d")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_steps(10));
+    assert!(pattern.find(&s, None).unwrap().is_some());
+    assert!(matches!(
+        pattern.gsub(&s, Repl::String(b"#"), None),
+        Err(Error::StepLimitExceeded { .. })
+    ));
+    let mut matches = pattern.gmatch(&s, None);
+    assert!(matches.next().unwrap().is_ok());
+    assert!(matches.any(|m| matches!(m, Err(Error::StepLimitExceeded { .. }))));
+    assert!(
+        pattern
+            .find_iter(&s, None)
+            .any(|m| matches!(m, Err(Error::StepLimitExceeded { .. })))
+    );
+
+    let pattern = pattern.with_limits(MatchLimits::new().with_max_steps(1000));
+    let (result, count) = pattern.gsub(&s, Repl::String(b"#"), None).unwrap();
+    assert_eq!(count, 90);
+    assert_eq!(result, b"# # # # # # # # # ".repeat(10));
+}
+
+#[test]
+fn test_max_captures() {
+    let pattern = Pattern::new(b"(a)(b)(c)")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_captures(2));
+    assert_eq!(
+        pattern.find(b"xabc", None),
+        Err(Error::TooManyCaptures {
+            span: Span::pattern(6..7)
+        })
+    );
+    assert_eq!(
+        pattern.is_match(b"xabc", None),
+        Err(Error::TooManyCaptures {
+            span: Span::pattern(6..7)
+        })
+    );
+    // Like Lua, the limit only matters once matching reaches the capture
+    assert_eq!(pattern.find(b"abx", None), Ok(None));
+
+    let pattern = Pattern::new(b"(a)()")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_captures(2));
+    assert!(pattern.find(b"a", None).unwrap().is_some());
+}
+
+#[test]
+fn test_max_depth() {
+    let pattern = Pattern::new(b"a")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_max_depth(50));
+    assert_eq!(pattern.limits().max_depth(), 50);
+    let pattern = pattern.with_limits(MatchLimits::new().with_max_depth(100));
+    assert_eq!(pattern.limits().max_depth(), 100);
+}
+
+#[test]
+fn test_cancelled() {
+    let flag = Arc::new(AtomicBool::new(true));
+    let pattern = Pattern::new(b"%d")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_cancel_flag(flag.clone()));
+    assert_eq!(
+        pattern.find(b"a1", None),
+        Err(Error::Cancelled {
+            span: Span::pattern(0..2)
+        })
+    );
+    assert_eq!(
+        pattern.gsub(b"a1", Repl::String(b"#"), None),
+        Err(Error::Cancelled {
+            span: Span::pattern(0..2)
+        })
+    );
+    flag.store(false, Ordering::Relaxed);
+    assert!(pattern.find(b"a1", None).unwrap().is_some());
+}
+
+#[test]
+fn test_cancelled_while_searching() {
+    // Without memoization, this search takes quadratic time. The deadline
+    // only stops the test from hanging if the flag is never checked
+    let flag = Arc::new(AtomicBool::new(false));
+    let limits = MatchLimits::new()
+        .with_cancel_flag(flag.clone())
+        .with_deadline(Instant::now() + Duration::from_secs(30));
+    let pattern = Pattern::new(b"(.-)%1%d").unwrap().with_limits(limits);
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(20));
+        flag.store(true, Ordering::Relaxed);
+    });
+    let s = b"a".repeat(100_000);
+    assert!(matches!(
+        pattern.find(&s, None),
+        Err(Error::Cancelled { .. })
+    ));
+    canceller.join().unwrap();
+}
+
+#[test]
+fn test_deadline() {
+    let pattern = Pattern::new(b"%d")
+        .unwrap()
+        .with_limits(MatchLimits::new().with_deadline(Instant::now()));
+    assert_eq!(
+        pattern.is_match(b"1", None),
+        Err(Error::DeadlineExceeded {
+            span: Span::pattern(0..2)
+        })
+    );
+
+    let limits = MatchLimits::new().with_deadline(Instant::now() + Duration::from_millis(20));
+    let pattern = Pattern::new(b"(.-)%1%d").unwrap().with_limits(limits);
+    let s = b"a".repeat(100_000);
+    assert!(matches!(
+        pattern.find(&s, None),
+        Err(Error::DeadlineExceeded { .. })
+    ));
+}
@@ -1,31 +1,53 @@
-use lsonar::r#match;
+use lsonar::{Capture, Result};
+use std::borrow::Cow;
+
+/// Runs `lsonar::r#match` and returns the same values as Lua.
+fn r#match<'a>(
+    text: &'a [u8],
+    pattern: &[u8],
+    init: Option<isize>,
+) -> Result<Option<Vec<Capture<'a>>>> {
+    lsonar::r#match(text, pattern, init).map(|c| c.map(|c| c.values().to_vec()))
+}
 
 #[test]
 fn test_simple_match() {
     assert_eq!(
         r#match(b"hello world", b"hello", None),
-        Ok(vec![b"hello".into()])
+        Ok(Some(vec![b"hello".into()]))
     );
     assert_eq!(
         r#match(b"hello world", b"world", None),
-        Ok(vec![b"world".into()])
+        Ok(Some(vec![b"world".into()]))
     );
-    assert_eq!(r#match(b"hello world", b"bye", None), Ok(vec![]));
+    assert_eq!(r#match(b"hello world", b"bye", None), Ok(None));
 }
 
 #[test]
 fn test_pattern_classes() {
-    assert_eq!(r#match(b"abc123", b"%a+", None), Ok(vec![b"abc".into()]));
-    assert_eq!(r#match(b"abc123", b"%d+", None), Ok(vec![b"123".into()]));
-    assert_eq!(r#match(b"abc123", b"[%D]+", None), Ok(vec![b"abc".into()]));
-    assert_eq!(r#match(b"abc123", b"[%A]+", None), Ok(vec![b"123".into()]));
+    assert_eq!(
+        r#match(b"abc123", b"%a+", None),
+        Ok(Some(vec![b"abc".into()]))
+    );
+    assert_eq!(
+        r#match(b"abc123", b"%d+", None),
+        Ok(Some(vec![b"123".into()]))
+    );
+    assert_eq!(
+        r#match(b"abc123", b"[%D]+", None),
+        Ok(Some(vec![b"abc".into()]))
+    );
+    assert_eq!(
+        r#match(b"abc123", b"[%A]+", None),
+        Ok(Some(vec![b"123".into()]))
+    );
 }
 
 #[test]
 fn test_single_capture() {
     assert_eq!(
         r#match(b"hello world", b"(hello)", None),
-        Ok(vec![b"hello".into()])
+        Ok(Some(vec![b"hello".into()]))
     );
 }
 
@@ -33,11 +55,11 @@ fn test_single_capture() {
 fn test_multiple_captures() {
     assert_eq!(
         r#match(b"hello world", b"(hello) (world)", None),
-        Ok(vec![b"hello".into(), b"world".into()])
+        Ok(Some(vec![b"hello".into(), b"world".into()]))
     );
     assert_eq!(
         r#match(b"123-456-7890", b"(%d+)%-(%d+)%-(%d+)", None),
-        Ok(vec![b"123".into(), b"456".into(), b"7890".into()])
+        Ok(Some(vec![b"123".into(), b"456".into(), b"7890".into()]))
     );
 }
 
@@ -45,7 +67,7 @@ fn test_multiple_captures() {
 fn test_combined_pattern_captures() {
     assert_eq!(
         r#match(b"abc123", b"(%a+)(%d+)", None),
-        Ok(vec![b"abc".into(), b"123".into()])
+        Ok(Some(vec![b"abc".into(), b"123".into()]))
     );
 }
 
@@ -53,7 +75,7 @@ fn test_combined_pattern_captures() {
 fn test_empty_captures() {
     assert_eq!(
         r#match(b"hello", b"(h)()ello", None),
-        Ok(vec![b"h".into(), b"2".into()])
+        Ok(Some(vec![b"h".into(), Capture::Position(2)]))
     );
 }
 
@@ -61,27 +83,87 @@ fn test_empty_captures() {
 fn test_init_parameter() {
     assert_eq!(
         r#match(b"hello world", b"world", Some(6)),
-        Ok(vec![b"world".into()])
+        Ok(Some(vec![b"world".into()]))
     );
     assert_eq!(
         r#match(b"hello world", b"hello", Some(1)),
-        Ok(vec![b"hello".into()])
+        Ok(Some(vec![b"hello".into()]))
     );
-    assert_eq!(r#match(b"hello world", b"hello", Some(2)), Ok(vec![]));
+    assert_eq!(r#match(b"hello world", b"hello", Some(2)), Ok(None));
 }
 
 #[test]
 fn test_empty_string_edge_cases() {
-    assert_eq!(r#match(b"", b"", None), Ok(vec![b"".into()]));
-    assert_eq!(r#match(b"", b"^$", None), Ok(vec![b"".into()]));
+    assert_eq!(r#match(b"", b"", None), Ok(Some(vec![b"".into()])));
+    assert_eq!(r#match(b"", b"^$", None), Ok(Some(vec![b"".into()])));
 }
 
 #[test]
 fn test_anchor_patterns() {
-    assert_eq!(r#match(b"hello", b"^", None), Ok(vec![b"".into()]));
-    assert_eq!(r#match(b"hello", b"$", None), Ok(vec![b"".into()]));
+    assert_eq!(r#match(b"hello", b"^", None), Ok(Some(vec![b"".into()])));
+    assert_eq!(r#match(b"hello", b"$", None), Ok(Some(vec![b"".into()])));
     assert_eq!(
         r#match(b"hello", b"^hello$", None),
-        Ok(vec![b"hello".into()])
+        Ok(Some(vec![b"hello".into()]))
+    );
+}
+
+#[test]
+fn test_typed_captures() {
+    let captures = lsonar::r#match(b"key=5", b"()(%w+)=(%d)", None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        captures.values(),
+        [Capture::Position(1), b"key".into(), b"5".into()]
+    );
+
+    assert_eq!(captures[1].position(), Some(1));
+    assert_eq!(captures[1].as_bytes(), None);
+    assert_eq!(captures[1].to_lua_bytes(), &b"1"[..]);
+
+    assert_eq!(captures[3].position(), None);
+    assert_eq!(captures[3].as_bytes(), Some(&b"5"[..]));
+    assert_eq!(Cow::from(captures[3]), &b"5"[..]);
+}
+
+#[test]
+fn test_captures() {
+    let captures = lsonar::r#match(b"let key = value;", b"(%w+) = (%w*)()", None)
+        .unwrap()
+        .unwrap();
+    assert_eq!(captures.len(), 4);
+    assert!(!captures.is_empty());
+
+    assert_eq!(captures[0], Capture::Str(b"key = value"));
+    assert_eq!(captures.range(0), Some(4..15));
+    assert_eq!(captures.get(1), Some(Capture::Str(b"key")));
+    assert_eq!(captures.range(1), Some(4..7));
+    assert_eq!(captures.get(2), Some(Capture::Str(b"value")));
+    assert_eq!(captures.range(2), Some(10..15));
+    assert_eq!(captures.get(3), Some(Capture::Position(16)));
+    assert_eq!(captures.range(3), Some(15..15));
+    assert_eq!(captures.get(4), None);
+    assert_eq!(captures.range(4), None);
+
+    assert_eq!(
+        captures.iter().collect::<Vec<_>>(),
+        [
+            Capture::Str(b"key = value"),
+            Capture::Str(b"key"),
+            Capture::Str(b"value"),
+            Capture::Position(16)
+        ]
     );
+    assert_eq!((&captures).into_iter().count(), 4);
+}
+
+#[test]
+fn test_no_match_vs_empty_match() {
+    assert_eq!(lsonar::r#match(b"abc", b"x", None), Ok(None));
+
+    let captures = lsonar::r#match(b"abc", b"x*", None).unwrap().unwrap();
+    assert_eq!(captures.len(), 1);
+    assert_eq!(captures.values(), [Capture::Str(b"")]);
+    assert_eq!(captures.range(0), Some(0..0));
 }
@@ -0,0 +1,120 @@
+use lsonar::{Capture, CaptureLocations, Indexing, Matcher, Pattern};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+/// Counts the allocations made by the current thread.
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn test_captures_read_at() {
+    let pattern = Pattern::new(b"()(%a+)=(%d+)").unwrap();
+    let mut matcher = Matcher::new();
+    let mut locations = CaptureLocations::new();
+    let s = b"x, key=10";
+    assert_eq!(
+        pattern.captures_read_at(&mut matcher, &mut locations, s, None),
+        Ok(Some(3..9))
+    );
+    assert_eq!(locations.len(), 4);
+    assert_eq!(locations.get(0), Some(3..9));
+    assert_eq!(locations.get(1), Some(3..3));
+    assert_eq!(locations.get(2), Some(3..6));
+    assert_eq!(locations.get(4), None);
+    assert_eq!(locations.capture(1, s), Some(Capture::Position(4)));
+    assert_eq!(locations.capture(3, s), Some(b"10".into()));
+
+    let pattern = pattern.with_indexing(Indexing::ZeroBased);
+    assert_eq!(
+        pattern.captures_read_at(&mut matcher, &mut locations, s, Some(1)),
+        Ok(Some(3..9))
+    );
+    assert_eq!(locations.capture(1, s), Some(Capture::Position(3)));
+
+    assert_eq!(
+        pattern.captures_read_at(&mut matcher, &mut locations, s, Some(7)),
+        Ok(None)
+    );
+    assert!(locations.is_empty());
+    assert_eq!(locations.get(0), None);
+    assert_eq!(locations.capture(1, s), None);
+}
+
+#[test]
+fn test_reused_for_literal() {
+    // A literal pattern is found without running the matcher, which must not
+    // leave the captures of the previous match behind
+    let mut matcher = Matcher::new();
+    let mut locations = CaptureLocations::new();
+    let pattern = Pattern::new(b"(%d)(%d)").unwrap();
+    assert_eq!(
+        pattern.captures_read_at(&mut matcher, &mut locations, b"12", None),
+        Ok(Some(0..2))
+    );
+    assert_eq!(locations.len(), 3);
+
+    let pattern = Pattern::new(b"abc").unwrap();
+    assert_eq!(
+        pattern.captures_read_at(&mut matcher, &mut locations, b"xabc", None),
+        Ok(Some(1..4))
+    );
+    assert_eq!(locations.len(), 1);
+    assert_eq!(locations.get(1), None);
+}
+
+#[test]
+fn test_does_not_allocate() {
+    let patterns = [
+        // Automaton with captures found by backtracking at the match start
+        &b"(%a)=(%d)"[..],
+        // Automaton with captures found by the Pike VM
+        b"(%w+)=(%d+)",
+        b"(%w*)(%d*);",
+        // Backtracking matcher
+        b"(%b[])=(%w+)",
+        b"(%a)%1",
+    ];
+    let s = b"a [x]=1; [y]=22; bb, key=333;".repeat(4);
+    let mut matcher = Matcher::new();
+    let mut locations = CaptureLocations::new();
+    for pattern in patterns {
+        let pattern = Pattern::new(pattern).unwrap();
+        let matches = |matcher: &mut Matcher, locations: &mut CaptureLocations| {
+            let mut count = 0;
+            let mut init = 1;
+            while let Some(range) = pattern
+                .captures_read_at(matcher, locations, &s, Some(init))
+                .unwrap()
+            {
+                count += 1;
+                init = range.end.max(range.start + 1) as isize + 1;
+            }
+            count
+        };
+        let expected = matches(&mut matcher, &mut locations);
+        assert!(expected > 0);
+
+        let before = ALLOCATIONS.with(Cell::get);
+        assert_eq!(matches(&mut matcher, &mut locations), expected);
+        assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::match_values;
+use lsonar::{Capture, find};
+
+#[test]
+fn test_exponential_backtracking() {
+    // `a?` repeated n times followed by `a` repeated n times takes 2^n steps
+    // to backtrack through
+    let mut pattern = b"a?".repeat(30);
+    pattern.extend(b"a".repeat(30));
+    let text = b"a".repeat(30);
+    assert_eq!(
+        find(&text, &pattern, None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(0..30))
+    );
+    assert_eq!(find(&text[1..], &pattern, None, false), Ok(None));
+}
+
+#[test]
+fn test_polynomial_backtracking() {
+    let text = b"ab".repeat(2000);
+    assert_eq!(find(&text, b".-.-.-.-x", None, false), Ok(None));
+    assert_eq!(find(&text, b"(.*)(.*)(.*)x", None, false), Ok(None));
+}
+
+#[test]
+fn test_deep_repetition() {
+    // The backtracking matcher would give up on this as too complex
+    let pattern = b"a?".repeat(600);
+    let text = b"a".repeat(600);
+    assert_eq!(
+        find(&text, &pattern, None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(0..600))
+    );
+}
+
+#[test]
+fn test_priority() {
+    assert_eq!(
+        match_values(b"aaab", b"(a-)(a*)b"),
+        Some(vec![b"".into(), b"aaa".into()])
+    );
+    assert_eq!(
+        match_values(b"aaab", b"(a?)(a?)(a*)"),
+        Some(vec![b"a".into(), b"a".into(), b"a".into()])
+    );
+    assert_eq!(
+        match_values(b"x = 1, y = 2", b"(%w+) = (.-),"),
+        Some(vec![b"x".into(), b"1".into()])
+    );
+    assert_eq!(
+        match_values(b"one two three", b"()(%a+)%s*()$"),
+        Some(vec![
+            Capture::Position(9),
+            b"three".into(),
+            Capture::Position(14)
+        ])
+    );
+}
+
+#[test]
+fn test_leftmost() {
+    assert_eq!(
+        find(b"xxabab", b"b*", None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(0..0))
+    );
+    assert_eq!(
+        find(b"xxaab", b"a+b", None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(2..5))
+    );
+    assert_eq!(
+        match_values(b"hello world", b"%f[%w](%w-)o"),
+        Some(vec![b"hell".into()])
+    );
+}
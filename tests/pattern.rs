@@ -0,0 +1,145 @@
+use lsonar::{Capture, Error, Match, Pattern, Repl, Result, Span};
+
+/// Returns the same values as Lua `string.find`.
+fn lua_find<'a>(m: Match<'a>) -> (usize, usize, Vec<Capture<'a>>) {
+    (m.start, m.end, m.captures.iter().skip(1).collect())
+}
+
+#[test]
+fn test_reuse() {
+    let pattern = Pattern::new(b"(%w+)=(%w+)").unwrap();
+    assert_eq!(
+        pattern.find(b"name=John", None).map(|m| m.map(lua_find)),
+        Ok(Some((1, 9, vec![b"name".into(), b"John".into()])))
+    );
+    assert_eq!(
+        pattern.find(b"age=25", None).map(|m| m.map(lua_find)),
+        Ok(Some((1, 6, vec![b"age".into(), b"25".into()])))
+    );
+    assert_eq!(pattern.find(b"nothing", None), Ok(None));
+}
+
+#[test]
+fn test_methods() {
+    let pattern = Pattern::new(b"%d+").unwrap();
+    assert_eq!(
+        pattern
+            .r#match(b"abc 123 def", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [b"123".into()]
+    );
+    assert_eq!(
+        pattern
+            .gmatch(b"1 22 333", None)
+            .map(|captures| captures.map(|captures| captures.values().to_vec()))
+            .collect::<Result<Vec<_>>>(),
+        Ok(vec![
+            vec![b"1".into()],
+            vec![b"22".into()],
+            vec![b"333".into()]
+        ])
+    );
+    assert_eq!(
+        pattern.gsub(b"1 22 333", Repl::String(b"<%0>"), Some(2)),
+        Ok((b"<1> <22> 333".to_vec(), 2))
+    );
+}
+
+#[test]
+fn test_anchored() {
+    let pattern = Pattern::new(b"^hello").unwrap();
+    assert_eq!(
+        pattern.find(b"hello world", None).map(|m| m.map(lua_find)),
+        Ok(Some((1, 5, vec![])))
+    );
+    assert_eq!(pattern.find(b"say hello", None), Ok(None));
+}
+
+#[test]
+fn test_rejects_malformed_pattern() {
+    assert_eq!(
+        Pattern::new(b"b%").unwrap_err(),
+        Error::EndsWithPercent {
+            span: Span::pattern(1..2)
+        }
+    );
+    assert_eq!(
+        Pattern::new(b"a[b").unwrap_err(),
+        Error::EndsWithoutBracket {
+            span: Span::pattern(1..3)
+        }
+    );
+    assert_eq!(
+        Pattern::new(b"a)").unwrap_err(),
+        Error::InvalidPatternCapture {
+            span: Span::pattern(1..2)
+        }
+    );
+    assert_eq!(
+        Pattern::new(b"(a%1)").unwrap_err(),
+        Error::InvalidCaptureIndex {
+            span: Span::pattern(2..4),
+            index: 1
+        }
+    );
+    assert_eq!(
+        Pattern::new(b"()%1").unwrap_err(),
+        Error::InvalidCaptureIndex {
+            span: Span::pattern(2..4),
+            index: 1
+        }
+    );
+    assert_eq!(
+        Pattern::new(b"a(b").unwrap_err(),
+        Error::UnfinishedCapture {
+            span: Span::pattern(1..2)
+        }
+    );
+    assert_eq!(
+        Pattern::new(b"%f%w").unwrap_err(),
+        Error::IncompleteFrontier {
+            span: Span::pattern(0..2)
+        }
+    );
+    assert_eq!(
+        Pattern::new(b"%bx").unwrap_err(),
+        Error::MissingBalanceArgs {
+            span: Span::pattern(0..3)
+        }
+    );
+    assert_eq!(
+        Pattern::new(&b"()".repeat(33)).unwrap_err(),
+        Error::TooManyCaptures {
+            span: Span::pattern(64..66)
+        }
+    );
+}
+
+#[test]
+fn test_lazy_errors() {
+    // The free functions only report syntax errors that the matcher reaches,
+    // like Lua
+    assert_eq!(lsonar::find(b"a", b"b%", None, false), Ok(None));
+    assert_eq!(
+        lsonar::find(b"b", b"b%", None, false),
+        Err(Error::EndsWithPercent {
+            span: Span::pattern(1..2)
+        })
+    );
+}
+
+#[test]
+fn test_send_sync_clone() {
+    fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+    assert_send_sync_clone::<Pattern>();
+
+    let pattern = Pattern::new(b"%a+").unwrap();
+    let handle = std::thread::spawn({
+        let pattern = pattern.clone();
+        move || pattern.find(b"123abc", None).map(|m| m.map(|m| m.start))
+    });
+    assert_eq!(handle.join().unwrap(), Ok(Some(4)));
+    assert_eq!(pattern.find(b"abc", None).unwrap().unwrap().end, 3);
+}
@@ -0,0 +1,103 @@
+use lsonar::{Capture, Repl, find, gmatch, gsub, r#match};
+
+/// Runs a plain [`find`] and returns the 0-based start of the match.
+fn find_plain(s: &[u8], pattern: &[u8]) -> Option<usize> {
+    find(s, pattern, None, true)
+        .unwrap()
+        .map(|m| m.range().start)
+}
+
+#[test]
+fn test_plain_find() {
+    assert_eq!(find_plain(b"hello world", b"o w"), Some(4));
+    assert_eq!(find_plain(b"hello world", b"world!"), None);
+    assert_eq!(find_plain(b"a.b.c", b".c"), Some(3));
+    assert_eq!(find_plain(b"aaaaaaab", b"aaab"), Some(4));
+    assert_eq!(find_plain(b"abababc", b"ababc"), Some(2));
+    assert_eq!(find_plain(b"", b""), Some(0));
+    assert_eq!(find_plain(b"", b"a"), None);
+}
+
+#[test]
+fn test_plain_find_init() {
+    let range = |init| {
+        find(b"abcabc", b"bc", Some(init), true)
+            .unwrap()
+            .map(|m| (m.start, m.end))
+    };
+    assert_eq!(range(1), Some((2, 3)));
+    assert_eq!(range(3), Some((5, 6)));
+    assert_eq!(range(-2), Some((5, 6)));
+    assert_eq!(range(6), None);
+
+    // Like Lua, an `init` after the end of the string never matches
+    assert_eq!(
+        find(b"abc", b"", Some(4), true).map(|m| m.map(|m| m.start)),
+        Ok(Some(4))
+    );
+    assert_eq!(find(b"abc", b"", Some(5), true), Ok(None));
+    assert_eq!(find(b"abc", b"", Some(5), false), Ok(None));
+}
+
+#[test]
+fn test_same_as_naive_search() {
+    // A small alphabet makes for many partial matches and periodic needles
+    let mut seed = 0x2545_f491_u32;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        b"ab"[(seed % 2) as usize]
+    };
+
+    for len in 2..12 {
+        for _ in 0..50 {
+            let needle = (0..len).map(|_| next()).collect::<Vec<_>>();
+            let haystack = (0..64).map(|_| next()).collect::<Vec<_>>();
+            let expected = haystack
+                .windows(needle.len())
+                .position(|window| window == needle);
+            assert_eq!(
+                find_plain(&haystack, &needle),
+                expected,
+                "{} in {}",
+                needle.escape_ascii(),
+                haystack.escape_ascii()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_no_specials() {
+    // Like Lua, `find` treats a pattern without special characters as plain
+    // text, even if it is not a valid pattern
+    assert_eq!(
+        find(b"f(a) b)", b"b)", None, false).map(|m| m.map(|m| (m.start, m.end))),
+        Ok(Some((6, 7)))
+    );
+    assert!(r#match(b"f(a) b)", b"b)", None).is_err());
+}
+
+#[test]
+fn test_literal_patterns() {
+    assert_eq!(
+        r#match(b"key=value", b"=v", None).map(|c| c.map(|c| c.values().to_vec())),
+        Ok(Some(vec![Capture::Str(b"=v")]))
+    );
+    assert_eq!(
+        gmatch(b"one, two, three", b", ", None)
+            .unwrap()
+            .map(|c| c.unwrap().range(0).unwrap())
+            .collect::<Vec<_>>(),
+        [3..5, 8..10]
+    );
+    assert_eq!(
+        gsub(b"a--b--c", b"%-%-", Repl::String(b"+"), None),
+        Ok((b"a+b+c".to_vec(), 2))
+    );
+    assert_eq!(
+        gsub(b"aaaa", b"aa", Repl::String(b"b"), Some(1)),
+        Ok((b"baa".to_vec(), 1))
+    );
+}
@@ -2,7 +2,7 @@
 //! Copyright (C) 1994-2025 Lua.org, PUC-Rio.
 //! SPDX-License-Identifier: MIT
 
-use lsonar::{self as string, Repl};
+use lsonar::{self as string, Capture, Repl};
 use std::{borrow::Cow, collections::HashMap};
 
 #[track_caller]
@@ -82,7 +82,17 @@ fn pu(p: &str) -> Vec<u8> {
     let (p, ..) = ok(string::gsub(
         p.as_bytes(),
         &[b"(", UTF8_CHARPATTERN, b")%?"].concat(),
-        Repl::Function(&mut |c| Some(ok(string::gsub(&c[0], b".", Repl::String(b"%0?"), None)).0)),
+        Repl::Function(&mut |c| {
+            Some(
+                ok(string::gsub(
+                    &c[0].to_lua_bytes(),
+                    b".",
+                    Repl::String(b"%0?"),
+                    None,
+                ))
+                .0,
+            )
+        }),
         None,
     ));
     // change '.' to utf-8 character patterns
@@ -185,7 +195,8 @@ fn gsub_dynamic_capture() {
         ));
         let (p, ..) = ok(string::gsub(&p, b"^(^?)", Repl::String(b"%1()"), Some(1)));
         let (p, ..) = ok(string::gsub(&p, b"($?)$", Repl::String(b"()%1"), Some(1)));
-        let t = string::r#match(s, &p, None).expect("matched");
+        let t = ok(string::r#match(s, &p, None)).expect("matched");
+        let t = t.values();
         &s[(to_number::<usize>(&t[0]) - 1)..to_number::<usize>(t.last().unwrap()) - 1]
     }
 
@@ -195,7 +206,7 @@ fn gsub_dynamic_capture() {
         &b"3= 4= 4 3"[..]
     );
     assert_eq!(f1(b"=======", b"^(=*)=%1$"), &b"======="[..]);
-    assert!(ok(string::r#match(b"==========", b"^([=]*)=%1$", None)).is_empty());
+    assert!(not_match(b"==========", b"^([=]*)=%1$"));
 }
 
 #[test]
@@ -217,7 +228,7 @@ fn gsub_strset() {
             ABC,
             p,
             Repl::Function(&mut |c| {
-                result.extend(c[0].as_ref());
+                result.extend(c[0].to_lua_bytes().as_ref());
                 None
             }),
             None,
@@ -241,8 +252,8 @@ fn gsub_strset() {
 #[test]
 fn match_capture() {
     #[track_caller]
-    fn to_refs<'a, 'b>(s: &'a [Cow<'b, [u8]>]) -> Vec<&'a [u8]> {
-        s.iter().map(|v| v.as_ref()).collect()
+    fn to_refs<'a>(s: &[Capture<'a>]) -> Vec<&'a [u8]> {
+        s.iter().map(|v| v.as_bytes().unwrap()).collect()
     }
 
     assert_eq!(r#match(b"alo xyzK", b"(%w+)K"), &b"xyz"[..]);
@@ -262,10 +273,17 @@ fn match_capture() {
             "â".as_bytes(),
             b"alo"
         ],
-        to_refs(&abcde).as_slice()
+        to_refs(abcde.unwrap().values()).as_slice()
     );
     let abcd = ok(string::r#match(b"0123456789", b"(.+(.?)())", None));
-    assert_eq!([&b"0123456789"[..], b"", b"11"], to_refs(&abcd).as_slice());
+    assert_eq!(
+        abcd.unwrap().values(),
+        [
+            Capture::Str(b"0123456789"),
+            Capture::Str(b""),
+            Capture::Position(11)
+        ]
+    );
 }
 
 #[test]
@@ -322,6 +340,7 @@ fn gsub_empty_533() {
     let mut i = 1;
     for cap in ok(string::gmatch(sub, b"()%s*()", None)) {
         let cap = cap.unwrap();
+        let cap = cap.values();
         res.extend(&sub[i - 1..to_number::<usize>(&cap[0]) - 1]);
         res.push(b'-');
         i = to_number::<usize>(&cap[1]);
@@ -339,7 +358,7 @@ fn gsub_nested_capture_literal() {
         ok(string::gsub(
             b"um (dois) tres (quatro)",
             b"(%(%w+%))",
-            Repl::Function(&mut |c| { Some(c[0].to_ascii_uppercase()) }),
+            Repl::Function(&mut |c| { Some(c[0].to_lua_bytes().to_ascii_uppercase()) }),
             None
         ))
         .0 == b"um (DOIS) tres (QUATRO)"
@@ -354,7 +373,7 @@ fn gsub_fn() {
         b"(%w+)=(%w%w*)",
         Repl::Function(&mut |caps| {
             let (n, v) = (&caps[0], &caps[1]);
-            globals.insert(n.to_vec(), v.to_vec());
+            globals.insert(n.to_lua_bytes().to_vec(), v.to_lua_bytes().to_vec());
             None
         }),
         None,
@@ -369,7 +388,7 @@ fn gsub_fn() {
     );
 
     fn f(a: &[lsonar::Capture<'_>]) -> Option<Vec<u8>> {
-        Some(gsub(&a[0], b".", &a[1]))
+        Some(gsub(&a[0].to_lua_bytes(), b".", &a[1].to_lua_bytes()))
     }
     assert!(
         ok(string::gsub(
@@ -404,7 +423,7 @@ fn gsub_position_capture() {
             let (a, w, b) = (&caps[0], &caps[1], &caps[2]);
             let a = to_number::<usize>(a);
             let len = to_number::<usize>(b) - a;
-            assert_eq!(w.len(), len);
+            assert_eq!(w.to_lua_bytes().len(), len);
             t.insert(a, len);
             None
         }),
@@ -452,7 +471,7 @@ fn gsub_fn_count() {
         b"first second word",
         b"%w%w*",
         Repl::Function(&mut |w| {
-            t.push(w[0].to_vec());
+            t.push(w[0].to_lua_bytes().to_vec());
             None
         }),
         None,
@@ -465,7 +484,7 @@ fn gsub_fn_count() {
             b"first second word",
             b"%w+",
             Repl::Function(&mut |w| {
-                t.push(w[0].to_vec());
+                t.push(w[0].to_lua_bytes().to_vec());
                 None
             }),
             Some(2)
@@ -519,8 +538,8 @@ fn gsub_recursive() {
             s,
             b"(.)(.+)",
             Repl::Function(&mut |c| {
-                let mut r = rev(&c[1]);
-                r.extend(c[0].iter());
+                let mut r = rev(&c[1].to_lua_bytes());
+                r.extend(c[0].to_lua_bytes().iter());
                 Some(r)
             }),
             None,
@@ -543,7 +562,7 @@ fn gsub_tables() {
         ok(string::gsub(
             b"alo alo",
             b"(.)",
-            Repl::Table(&|k| match k.as_ref() {
+            Repl::Table(&|k| match k.to_lua_bytes().as_ref() {
                 b"a" => Some(b"AA".to_vec()),
                 b"l" => Some(b"".to_vec()),
                 _ => None,
@@ -556,7 +575,7 @@ fn gsub_tables() {
         ok(string::gsub(
             b"alo alo",
             b"(.).",
-            Repl::Table(&|k| match k.as_ref() {
+            Repl::Table(&|k| match k.to_lua_bytes().as_ref() {
                 b"a" => Some(b"AA".to_vec()),
                 b"l" => Some(b"K".to_vec()),
                 _ => None,
@@ -569,7 +588,7 @@ fn gsub_tables() {
         ok(string::gsub(
             b"alo alo",
             b"((.)(.?))",
-            Repl::Table(&|k| match k.as_ref() {
+            Repl::Table(&|k| match k.to_lua_bytes().as_ref() {
                 b"al" => Some(b"AA".to_vec()),
                 // XXX: This kind of type confusion is impossible in Rust
                 b"o" => None,
@@ -584,7 +603,7 @@ fn gsub_tables() {
         ok(string::gsub(
             b"alo alo",
             b"().",
-            Repl::Table(&|k| match k.as_ref() {
+            Repl::Table(&|k| match k.to_lua_bytes().as_ref() {
                 b"1" => Some(b"x".to_vec()),
                 b"2" => Some(b"yy".to_vec()),
                 b"3" => Some(b"zzz".to_vec()),
@@ -599,7 +618,7 @@ fn gsub_tables() {
         ok(string::gsub(
             b"a alo b hi",
             b"%w%w+",
-            Repl::Table(&|k| Some(k.to_ascii_uppercase())),
+            Repl::Table(&|k| Some(k.to_lua_bytes().to_ascii_uppercase())),
             None
         ))
         .0 == b"a ALO b HI"
@@ -612,6 +631,7 @@ fn gmatch_basic() {
     let mut a = 0;
     for i in ok(string::gmatch(b"abcde", b"()", None)) {
         let i = i.unwrap();
+        let i = i.values();
         let i = to_number::<i32>(&i[0]);
         assert_eq!(i, a + 1);
         a = i;
@@ -621,13 +641,15 @@ fn gmatch_basic() {
     let mut t = vec![];
     for w in ok(string::gmatch(b"first second word", b"%w+", None)) {
         let w = w.unwrap();
-        t.push(w[0].to_vec());
+        let w = w.values();
+        t.push(w[0].to_lua_bytes().to_vec());
     }
     assert_eq!(t, [&b"first"[..], b"second", b"word"]);
 
     let mut t = vec![3, 6, 9];
     for i in ok(string::gmatch(b"xuxx uu ppar r", b"()(.)%2", None)) {
         let i = i.unwrap();
+        let i = i.values();
         assert_eq!(to_number::<i32>(&i[0]), t.remove(0));
     }
     assert!(t.is_empty());
@@ -639,6 +661,7 @@ fn gmatch_basic() {
         None,
     )) {
         let ij = ij.unwrap();
+        let ij = ij.values();
         let (i, j) = (&ij[0], &ij[1]);
         t.insert(to_number::<i32>(i), to_number::<i32>(j));
     }
@@ -656,6 +679,7 @@ fn gmatch_init_param() {
     let mut s = 0;
     for k in ok(string::gmatch(b"10 20 30", b"%d+", Some(3))) {
         let k = k.unwrap();
+        let k = k.values();
         s += to_number::<i32>(&k[0]);
     }
     assert_eq!(s, 50);
@@ -663,6 +687,7 @@ fn gmatch_init_param() {
     let mut s = 0;
     for k in ok(string::gmatch(b"11 21 31", b"%d+", Some(-4))) {
         let k = k.unwrap();
+        let k = k.values();
         s += to_number::<i32>(&k[0]);
     }
     assert_eq!(s, 32);
@@ -709,6 +734,7 @@ fn pattern_frontiers() {
     let mut a = vec![1, 5, 9, 14, 17];
     for k in ok(string::gmatch(b"alo alo th02 is 1hat", b"()%f[%w%d]", None)) {
         let k = k.unwrap();
+        let k = k.values();
         assert_eq!(a.remove(0), to_number::<i32>(&k[0]));
     }
     assert!(a.is_empty());
@@ -774,7 +800,7 @@ fn gsub_reuse() {
         &s,
         b".",
         Repl::Function(&mut |x| {
-            assert_eq!(x[0], &b"a"[..]);
+            assert_eq!(x[0], Capture::Str(b"a"));
             count += 1;
             None // no substitution
         }),
@@ -789,9 +815,9 @@ fn gsub_reuse() {
         &s,
         b".",
         Repl::Function(&mut |x| {
-            assert_eq!(x[0], &b"a"[..]);
+            assert_eq!(x[0], Capture::Str(b"a"));
             count += 1;
-            Some(x[0].to_vec()) // substitution...
+            Some(x[0].to_lua_bytes().to_vec()) // substitution...
         }),
         None,
     ));
@@ -835,23 +861,23 @@ fn not_find(s: &[u8], pattern: &[u8]) -> bool {
 #[track_caller]
 fn r#match<'a>(s: &'a [u8], pattern: &[u8]) -> Cow<'a, [u8]> {
     ok(string::r#match(s, pattern, None))
-        .into_iter()
-        .next()
         .expect("should find match")
+        .values()[0]
+        .to_lua_bytes()
 }
 
 #[track_caller]
 fn not_match(s: &[u8], pattern: &[u8]) -> bool {
-    ok(string::r#match(s, pattern, None)).is_empty()
+    ok(string::r#match(s, pattern, None)).is_none()
 }
 
 #[track_caller]
-fn to_number<T>(s: &[u8]) -> T
+fn to_number<T>(c: &Capture<'_>) -> T
 where
     T: core::str::FromStr,
     <T as core::str::FromStr>::Err: core::fmt::Debug,
 {
-    str::from_utf8(s)
+    str::from_utf8(&c.to_lua_bytes())
         .expect("should be valid utf-8")
         .parse::<T>()
         .expect("should be a valid integer")
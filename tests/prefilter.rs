@@ -0,0 +1,123 @@
+mod common;
+
+use common::find_range;
+use lsonar::{Capture, Error, Span, gsub, is_match, r#match};
+
+#[test]
+fn test_literal_prefix() {
+    let log = b"INFO: ok\nERROR: 42\nERROR: x\nERROR: 7";
+    assert_eq!(find_range(log, b"ERROR: (%d+)", None), Some((10, 18)));
+    assert_eq!(find_range(log, b"ERROR: (%d+)", Some(11)), Some((29, 36)));
+    assert_eq!(find_range(log, b"ERROR: %a", None), Some((20, 27)));
+    assert_eq!(find_range(log, b"WARN: ", None), None);
+    assert_eq!(find_range(b"aab", b"ab", None), Some((2, 3)));
+    assert_eq!(find_range(b"ababac", b"abac", None), Some((3, 6)));
+    assert_eq!(find_range(b"abc", b"abcd", None), None);
+}
+
+#[test]
+fn test_leading_captures() {
+    assert_eq!(
+        r#match(b"x = key: value", b"()(key): (%a+)", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [Capture::Position(5), b"key".into(), b"value".into()]
+    );
+    assert_eq!(find_range(b"xxab", b"(a)b", None), Some((3, 4)));
+}
+
+#[test]
+fn test_leading_quantifiers() {
+    assert_eq!(find_range(b"xxaaab", b"a+b", None), Some((3, 6)));
+    assert_eq!(find_range(b"xxaaab", b"xa+b", None), Some((2, 6)));
+    // Optional leading items must not be required by the scan
+    assert_eq!(find_range(b"xxb", b"a*b", None), Some((3, 3)));
+    assert_eq!(find_range(b"xxb", b"a?b", None), Some((3, 3)));
+    assert_eq!(find_range(b"xxb", b"a-b", None), Some((3, 3)));
+    assert_eq!(find_range(b"", b"a*", None), Some((1, 0)));
+}
+
+#[test]
+fn test_leading_byte_set() {
+    assert_eq!(find_range(b"abc 123", b"%d+", None), Some((5, 7)));
+    assert_eq!(find_range(b"abc 123", b"[1-3]", Some(-2)), Some((6, 6)));
+    assert_eq!(find_range(b"abc", b"%d", None), None);
+    assert_eq!(find_range(b"ab(c)d", b"%b()", None), Some((3, 5)));
+    assert_eq!(find_range(b"xyz", b".", Some(3)), Some((3, 3)));
+}
+
+#[test]
+fn test_required_literal() {
+    let email = b"(%w+)@(%w+)%.com";
+    assert_eq!(
+        find_range(b"mail bob@example.com", email, None),
+        Some((6, 20))
+    );
+    assert_eq!(find_range(b"mail bob@example.org", email, None), None);
+    assert_eq!(find_range(b"a.com b@c.co", email, None), None);
+    // The literal has to come after the start of the search
+    assert_eq!(find_range(b"x@y.com z", email, Some(4)), None);
+    assert_eq!(find_range(b"x.com ab", b"%a+%.com", Some(2)), None);
+    assert_eq!(
+        r#match(b"k1=v1 key=value", b"(%a+)=(%a+)", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [b"key".into(), b"value".into()]
+    );
+    // A balance keeps the pattern on the backtracking matcher
+    assert_eq!(
+        find_range(b"(x)=1 (y)=:2", b"%b()=:%d", None),
+        Some((7, 12))
+    );
+    assert_eq!(find_range(b"(x)=1 (y)=2", b"%b()=:%d", None), None);
+}
+
+#[test]
+fn test_min_len() {
+    assert_eq!(find_range(b"12-34", b"%d%d%-%d%d%d", None), None);
+    assert_eq!(find_range(b"12-345", b"%d%d%-%d%d%d", None), Some((1, 6)));
+    assert_eq!(find_range(b"12-345", b"%d%d%-%d%d%d", Some(2)), None);
+    assert_eq!(find_range(b"(a)", b"x?%b()", None), Some((1, 3)));
+    assert_eq!(find_range(b"(a)", b"x?%b()", Some(3)), None);
+    assert_eq!(find_range(b"ab", b"a+b+", None), Some((1, 2)));
+    assert_eq!(find_range(b"", b"a-b*", None), Some((1, 0)));
+}
+
+#[test]
+fn test_anchored() {
+    assert_eq!(find_range(b"xab", b"^ab", None), None);
+    assert_eq!(find_range(b"xab", b"^ab", Some(2)), Some((2, 3)));
+}
+
+#[test]
+fn test_gsub() {
+    assert_eq!(
+        gsub(
+            b"id=1, id=22, idx=3",
+            b"id=(%d+)",
+            lsonar::Repl::String(b"<%1>"),
+            None
+        ),
+        Ok((b"<1>, <22>, idx=3".to_vec(), 2))
+    );
+}
+
+#[test]
+fn test_lazy_errors() {
+    // The matcher only reaches the error where the prefix matches
+    assert_eq!(is_match(b"xyz", b"ab%", None), Ok(false));
+    assert_eq!(
+        is_match(b"xyzab", b"ab%", None),
+        Err(Error::EndsWithPercent {
+            span: Span::pattern(2..3)
+        })
+    );
+    assert_eq!(
+        is_match(b"xyzab", b"(ab", None),
+        Err(Error::UnfinishedCapture {
+            span: Span::pattern(0..1)
+        })
+    );
+}
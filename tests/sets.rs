@@ -0,0 +1,87 @@
+use lsonar::{
+    ast::{self, NodeKind},
+    find, is_match,
+};
+
+/// Checks that the matcher accepts exactly the bytes which the parsed
+/// character class matches.
+fn check_class(class: &[u8]) {
+    let nodes = ast::parse(class).unwrap().nodes;
+    let NodeKind::Single(single) = &nodes[0].kind else {
+        panic!("{} is not a single class", class.escape_ascii());
+    };
+
+    let mut pattern = b"^".to_vec();
+    pattern.extend(class);
+    for c in 0..=u8::MAX {
+        assert_eq!(
+            is_match(&[c], &pattern, None),
+            Ok(single.matches(c)),
+            "{} with {c}",
+            class.escape_ascii()
+        );
+    }
+}
+
+#[test]
+fn test_classes() {
+    for class in [
+        &b"a"[..],
+        b".",
+        b"%a",
+        b"%A",
+        b"%c",
+        b"%d",
+        b"%g",
+        b"%l",
+        b"%p",
+        b"%s",
+        b"%u",
+        b"%w",
+        b"%x",
+        b"%z",
+        b"%Z",
+        b"%%",
+        b"%]",
+    ] {
+        check_class(class);
+    }
+}
+
+#[test]
+fn test_sets() {
+    for class in [
+        &b"[abc]"[..],
+        b"[^abc]",
+        b"[a-z]",
+        b"[^a-z%d]",
+        b"[%w_]",
+        b"[%]]",
+        b"[]]",
+        b"[^]]",
+        b"[a-]",
+        b"[-a]",
+        b"[z-a]",
+        b"[%z]",
+        b"[^%z]",
+        b"[\0-\x7f]",
+        b"[\x80-\xff]",
+    ] {
+        check_class(class);
+    }
+}
+
+#[test]
+fn test_frontier() {
+    let range = |s: &[u8], pattern: &[u8]| {
+        find(s, pattern, None, false)
+            .unwrap()
+            .map(|m| (m.start, m.end))
+    };
+    assert_eq!(range(b"THE (quick) fox", b"%f[%a]%a+"), Some((1, 3)));
+    assert_eq!(range(b"THE (quick) fox", b"%f[%l]%a+"), Some((6, 10)));
+    // The ends of the subject are treated as `\0`
+    assert_eq!(range(b"abc", b"%f[%z]"), Some((4, 3)));
+    assert_eq!(range(b"abc", b"%f[^%z]"), Some((1, 0)));
+    assert_eq!(range(b"a\0b", b"%f[%Z]b"), Some((3, 3)));
+}
@@ -0,0 +1,167 @@
+use lsonar::{Error, Repl, Span, strict, validate, validate_all};
+
+#[test]
+fn test_validate() {
+    assert_eq!(validate(b"^(%w+)%s*=%s*(%w+)$"), Ok(()));
+    assert_eq!(validate(b"[%]]%b()%f[%a]"), Ok(()));
+    assert_eq!(
+        validate(b"b%"),
+        Err(Error::EndsWithPercent {
+            span: Span::pattern(1..2)
+        })
+    );
+    assert_eq!(
+        validate(b"x[a"),
+        Err(Error::EndsWithoutBracket {
+            span: Span::pattern(1..3)
+        })
+    );
+    assert_eq!(
+        validate(b"(x"),
+        Err(Error::UnfinishedCapture {
+            span: Span::pattern(0..1)
+        })
+    );
+    assert_eq!(
+        validate(b"x%2"),
+        Err(Error::InvalidCaptureIndex {
+            span: Span::pattern(1..3),
+            index: 2
+        })
+    );
+}
+
+#[test]
+fn test_validate_all() {
+    assert_eq!(validate_all(b"(%w+)=[^%s]*%1$"), Ok(()));
+    assert_eq!(
+        validate_all(b"[a-z%9%"),
+        Err(vec![
+            Error::EndsWithoutBracket {
+                span: Span::pattern(0..7)
+            },
+            Error::InvalidCaptureIndex {
+                span: Span::pattern(4..6),
+                index: 9
+            },
+            Error::EndsWithPercent {
+                span: Span::pattern(6..7)
+            },
+        ])
+    );
+    assert_eq!(
+        validate_all(b"^(a))%f(%b"),
+        Err(vec![
+            Error::InvalidPatternCapture {
+                span: Span::pattern(4..5)
+            },
+            Error::IncompleteFrontier {
+                span: Span::pattern(5..7)
+            },
+            Error::UnfinishedCapture {
+                span: Span::pattern(7..8)
+            },
+            Error::MissingBalanceArgs {
+                span: Span::pattern(8..10)
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_validate_all_captures() {
+    let mut pattern = b"()".repeat(32);
+    pattern.extend(b"(a)()%1");
+    assert_eq!(
+        validate_all(&pattern),
+        Err(vec![
+            Error::TooManyCaptures {
+                span: Span::pattern(64..65)
+            },
+            Error::TooManyCaptures {
+                span: Span::pattern(67..69)
+            },
+            Error::InvalidCaptureIndex {
+                span: Span::pattern(69..71),
+                index: 1
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_unreached_errors() {
+    // The lenient functions never reach the trailing `%` because `b` fails
+    // first
+    assert_eq!(lsonar::find(b"a", b"b%", None, false), Ok(None));
+    assert_eq!(lsonar::r#match(b"a", b"b%", None), Ok(None));
+    assert_eq!(
+        lsonar::gsub(b"a", b"b%", Repl::String(b"x"), None),
+        Ok((b"a".to_vec(), 0))
+    );
+    assert_eq!(lsonar::gmatch(b"a", b"b%", None).unwrap().count(), 0);
+    assert_eq!(lsonar::find_iter(b"a", b"b%", None).unwrap().count(), 0);
+    assert_eq!(lsonar::is_match(b"a", b"b%", None), Ok(false));
+
+    let error = Error::EndsWithPercent {
+        span: Span::pattern(1..2),
+    };
+    assert_eq!(strict::find(b"a", b"b%", None, false), Err(error.clone()));
+    assert_eq!(strict::r#match(b"a", b"b%", None), Err(error.clone()));
+    assert_eq!(
+        strict::gsub(b"a", b"b%", Repl::String(b"x"), None),
+        Err(error.clone())
+    );
+    assert_eq!(strict::gmatch(b"a", b"b%", None).err(), Some(error.clone()));
+    assert_eq!(
+        strict::find_iter(b"a", b"b%", None).err(),
+        Some(error.clone())
+    );
+    assert_eq!(strict::is_match(b"a", b"b%", None), Err(error));
+}
+
+#[test]
+fn test_valid_patterns() {
+    assert_eq!(
+        strict::find(b"hello world", b"o w", None, false).map(|m| m.map(|m| m.range())),
+        Ok(Some(4..7))
+    );
+    assert_eq!(
+        strict::find(b"a.b", b".", None, true).map(|m| m.map(|m| m.range())),
+        Ok(Some(1..2))
+    );
+    assert_eq!(
+        strict::r#match(b"key = value", b"(%w+)%s*=%s*(%w+)", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [b"key".into(), b"value".into()]
+    );
+    assert_eq!(
+        strict::gmatch(b"one two", b"%a+", None)
+            .unwrap()
+            .map(|captures| captures.map(|captures| captures.values().to_vec()))
+            .collect::<Result<Vec<_>, _>>(),
+        Ok(vec![vec![b"one".into()], vec![b"two".into()]])
+    );
+    assert_eq!(
+        strict::find_iter(b"one two", b"%a+", None)
+            .unwrap()
+            .map(|m| m.map(|m| m.range()))
+            .collect::<Result<Vec<_>, _>>(),
+        Ok(vec![0..3, 4..7])
+    );
+    assert_eq!(strict::is_match(b"hello", b"l+o$", None), Ok(true));
+    assert_eq!(
+        strict::gsub(b"hello world", b"o", Repl::String(b"0"), None),
+        Ok((b"hell0 w0rld".to_vec(), 2))
+    );
+}
+
+#[test]
+fn test_plain_is_not_validated() {
+    assert_eq!(
+        strict::find(b"50%", b"%", None, true).map(|m| m.map(|m| m.range())),
+        Ok(Some(2..3))
+    );
+}
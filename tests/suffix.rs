@@ -0,0 +1,70 @@
+mod common;
+
+use common::find_range;
+use lsonar::{Capture, Pattern, is_match, r#match};
+
+#[test]
+fn test_extension() {
+    let pattern = Pattern::new(b"%.(%w+)$").unwrap();
+    let extension = |path: &[u8]| {
+        pattern
+            .r#match(path, None)
+            .unwrap()
+            .map(|captures| captures[1].as_bytes().unwrap().to_vec())
+    };
+    assert_eq!(extension(b"/var/log/app.2024.log"), Some(b"log".to_vec()));
+    assert_eq!(extension(b"archive.tar.gz"), Some(b"gz".to_vec()));
+    assert_eq!(extension(b"README"), None);
+    assert_eq!(extension(b"dir.d/"), None);
+    assert_eq!(extension(b""), None);
+}
+
+#[test]
+fn test_leftmost_start() {
+    assert_eq!(find_range(b"aaa", b"a*$", None), Some((1, 3)));
+    assert_eq!(find_range(b"abab", b"ab$", None), Some((3, 4)));
+    assert_eq!(find_range(b"abc", b"$", None), Some((4, 3)));
+    assert_eq!(find_range(b"x  y  ", b"%s*$", None), Some((5, 6)));
+    assert_eq!(find_range(b"one two", b"%f[%w]%w+$", None), Some((5, 7)));
+    assert_eq!(find_range(b"one two", b"%f[%w]%w+$", Some(6)), None);
+    assert_eq!(find_range(b"ab", b"b$", Some(4)), None);
+    assert_eq!(find_range(b"aab", b"^a*b$", None), Some((1, 3)));
+    assert_eq!(find_range(b"aab", b"^a*b$", Some(3)), Some((3, 3)));
+}
+
+#[test]
+fn test_literal_suffix() {
+    assert_eq!(find_range(b"a.txt", b"%.txt$", None), Some((2, 5)));
+    assert_eq!(find_range(b"a.txt", b"%.txt$", Some(3)), None);
+    assert_eq!(find_range(b"a.txt.bak", b"%.txt$", None), None);
+    assert_eq!(is_match(b"notes.md", b"(%a+)%.md()$", None), Ok(true));
+    assert_eq!(is_match(b"notes.mdx", b"(%a+)%.md()$", None), Ok(false));
+}
+
+#[test]
+fn test_backtracking_matcher() {
+    // Balances and back-references keep the pattern on the backtracking
+    // matcher
+    assert_eq!(
+        r#match(b"f(a)(b) g(c)", b"(%a)%b()$", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [b"g".into()]
+    );
+    assert_eq!(
+        r#match(b"xyzzy abba", b"(%a)(%a)%2%1$", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [b"a".into(), b"b".into()]
+    );
+    assert_eq!(
+        r#match(b"a=1 b=22", b"()(%a)=%d%d$", None)
+            .unwrap()
+            .unwrap()
+            .values(),
+        [Capture::Position(5), b"b".into()]
+    );
+    assert_eq!(find_range(b"(a)", b"%b()$", Some(2)), None);
+}